@@ -1,3 +1,6 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::Path;
 use std::process::Command;
 
 fn main() {
@@ -8,6 +11,15 @@ fn main() {
         .process_current_dir()
         .unwrap();
 
+    // Generate the AST visitors from the node definitions
+    let nodes = std::fs::read_to_string("src/s1_parser/ast/node.rs").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(
+        Path::new(&out_dir).join("visitor.rs"),
+        generate_visitors(&nodes),
+    )
+    .unwrap();
+
     // Attempt to retrieve the current Git version
     let output = Command::new("git")
         .args(["describe", "--dirty", "--tags", "--long"])
@@ -34,3 +46,205 @@ fn main() {
     println!("cargo:rerun-if-changed=.git/HEAD");
     println!("cargo:rerun-if-changed=.git/refs/");
 }
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+// Visitor Generation
+
+/// A node declared in `ast/node.rs`, with its fields or enum variants
+/// as (name, type) pairs. Unit variants have an empty type.
+struct Node {
+    name: String,
+    is_enum: bool,
+    members: Vec<(String, String)>,
+}
+
+/// Scans the node definitions, relying on rustfmt formatting.
+fn parse_nodes(source: &str) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut current: Option<Node> = None;
+    for line in source.lines() {
+        let line = line.trim();
+        if let Some(node) = current.as_mut() {
+            if line == "}" {
+                nodes.push(current.take().unwrap());
+            } else if line.starts_with("//") || line.starts_with("#[") {
+                continue;
+            } else if node.is_enum {
+                let variant = line.trim_end_matches(',');
+                match variant.split_once('(') {
+                    Some((name, ty)) => node
+                        .members
+                        .push((name.to_string(), ty.trim_end_matches(')').to_string())),
+                    None => node.members.push((variant.to_string(), String::new())),
+                }
+            } else if let Some((name, ty)) =
+                line.strip_prefix("pub ").and_then(|l| l.split_once(": "))
+            {
+                node.members
+                    .push((name.to_string(), ty.trim_end_matches(',').to_string()));
+            }
+        } else {
+            for (prefix, is_enum) in [("pub struct ", false), ("pub enum ", true)] {
+                if let Some(name) = line.strip_prefix(prefix) {
+                    current = Some(Node {
+                        name: name.trim_end_matches(" {").to_string(),
+                        is_enum,
+                        members: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+    nodes
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// Borrows a place expression, `*item` is already a reference.
+fn borrow(place: &str, mutable: bool) -> String {
+    match place.strip_prefix('*') {
+        Some(item) if item == "item" => item.to_string(),
+        _ if mutable => format!("&mut {}", place),
+        _ => format!("&{}", place),
+    }
+}
+
+/// Emits the code visiting all nodes held by the place of type `ty`,
+/// or nothing if it does not contain any node.
+fn walk_place(place: &str, ty: &str, nodes: &HashSet<String>, mutable: bool) -> Option<String> {
+    let suffix = if mutable { "_mut" } else { "" };
+    let inner = |prefix: &str| ty.strip_prefix(prefix).map(|t| &t[..t.len() - 1]);
+    if let Some(inner) = inner("Box<") {
+        // boxed nodes are passed as is, relying on deref coercion
+        let place = if nodes.contains(inner) {
+            place.to_string()
+        } else {
+            format!("*{}", place)
+        };
+        walk_place(&place, inner, nodes, mutable)
+    } else if let Some(inner) = inner("Vec<Option<") {
+        let body = walk_place("*item", &inner[..inner.len() - 1], nodes, mutable)?;
+        Some(format!(
+            "for item in {}.iter{}().flatten() {{ {} }}",
+            place.trim_start_matches('*'),
+            suffix,
+            body
+        ))
+    } else if let Some(inner) = inner("Option<") {
+        let body = walk_place("*item", inner, nodes, mutable)?;
+        Some(format!(
+            "if let Some(item) = {} {{ {} }}",
+            borrow(place, mutable),
+            body
+        ))
+    } else if let Some(inner) = inner("Vec<") {
+        let body = walk_place("*item", inner, nodes, mutable)?;
+        Some(format!(
+            "for item in {} {{ {} }}",
+            borrow(place, mutable),
+            body
+        ))
+    } else if let Some(inner) = inner("IndexMap<String, ") {
+        let body = walk_place("*item", inner, nodes, mutable)?;
+        Some(format!(
+            "for item in {}.values{}() {{ {} }}",
+            place.trim_start_matches('*'),
+            suffix,
+            body
+        ))
+    } else if nodes.contains(ty) {
+        Some(format!(
+            "visitor.visit_{}{}({});",
+            snake_case(ty),
+            suffix,
+            borrow(place, mutable)
+        ))
+    } else {
+        None
+    }
+}
+
+/// Generates the `Visitor` and `VisitorMut` traits with their walk functions.
+fn generate_visitors(source: &str) -> String {
+    let nodes = parse_nodes(source);
+    let names: HashSet<String> = nodes.iter().map(|node| node.name.clone()).collect();
+    let mut out = String::new();
+    for mutable in [false, true] {
+        let (suffix, trait_name, reference) = if mutable {
+            ("_mut", "VisitorMut", "&mut ")
+        } else {
+            ("", "Visitor", "&")
+        };
+        writeln!(out, "pub trait {} {{", trait_name).unwrap();
+        for node in &nodes {
+            let snake = snake_case(&node.name);
+            writeln!(
+                out,
+                "fn enter_{s}{m}(&mut self, _node: {r}{n}) {{}}\n\
+                 fn exit_{s}{m}(&mut self, _node: {r}{n}) {{}}\n\
+                 fn visit_{s}{m}(&mut self, node: {r}{n}) {{\n\
+                 self.enter_{s}{m}(node);\n\
+                 walk_{s}{m}(self, node);\n\
+                 self.exit_{s}{m}(node);\n\
+                 }}",
+                s = snake,
+                m = suffix,
+                r = reference,
+                n = node.name,
+            )
+            .unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+
+        for node in &nodes {
+            let mut body = Vec::new();
+            if node.is_enum {
+                let arms: Vec<String> = node
+                    .members
+                    .iter()
+                    .map(
+                        |(variant, ty)| match walk_place("*item", ty, &names, mutable) {
+                            Some(walk) => {
+                                format!("{}::{}(item) => {{ {} }}", node.name, variant, walk)
+                            }
+                            None if ty.is_empty() => format!("{}::{} => {{}}", node.name, variant),
+                            None => format!("{}::{}(_) => {{}}", node.name, variant),
+                        },
+                    )
+                    .collect();
+                body.push(format!("match node {{ {} }}", arms.join(" ")));
+            } else {
+                for (field, ty) in &node.members {
+                    if let Some(walk) = walk_place(&format!("node.{}", field), ty, &names, mutable)
+                    {
+                        body.push(walk);
+                    }
+                }
+            }
+            let unused = if body.is_empty() { "_" } else { "" };
+            writeln!(
+                out,
+                "/// Visits the children of `{n}`.\n\
+                 pub fn walk_{s}{m}<V: {t} + ?Sized>({u}visitor: &mut V, {u}node: {r}{n}) {{\n{b}\n}}",
+                n = node.name,
+                s = snake_case(&node.name),
+                m = suffix,
+                t = trait_name,
+                u = unused,
+                r = reference,
+                b = body.join("\n"),
+            )
+            .unwrap();
+        }
+    }
+    out
+}
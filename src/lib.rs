@@ -5,4 +5,9 @@ pub mod s1_parser;
 extern crate macro_rules_attribute;
 
 pub use s1_parser::ast;
-pub use s1_parser::{parse, parse_file};
+pub use s1_parser::formatter;
+pub use s1_parser::source_map::{LineCol, LineIndex, SourceMap};
+pub use s1_parser::{
+    parse, parse_file, parse_with_recovery, parse_with_source_map, ParseDiagnostics,
+    ParseFileError, SyntaxError,
+};
@@ -1,16 +1,129 @@
-use clap::Parser;
+use clap::{ArgAction, Parser, Subcommand};
+use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+use rumoca_parser::formatter::{self, FormatOptions};
+use std::path::{Path, PathBuf};
+use std::{fs, io, process};
 
 #[derive(Parser, Debug)]
 #[command(version, about = "Rumoca Modelica Parser", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The model file to compile
-    #[arg(name = "MODELICA_FILE")]
-    model_file: String,
+    #[arg(name = "MODELICA_FILE", required = true)]
+    model_file: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Format Modelica files in place
+    Fmt(FmtArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct FmtArgs {
+    /// The model files, or directories to search for .mo files
+    #[arg(name = "PATH", required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Print a diff of the files that are not formatted and exit with an
+    /// error, instead of formatting them
+    #[arg(long)]
+    check: bool,
+
+    /// Spaces per indentation level
+    #[arg(long, default_value_t = 4)]
+    indent_width: usize,
+
+    /// Lines longer than this are wrapped where possible
+    #[arg(long, default_value_t = 100)]
+    line_width: usize,
+
+    /// Print each component of a clause on its own line
+    #[arg(long, default_value_t = true, action = ArgAction::Set)]
+    split_declarations: bool,
+
+    /// Align the descriptions of consecutive components
+    #[arg(long, default_value_t = true, action = ArgAction::Set)]
+    align_descriptions: bool,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
-    let def = rumoca_parser::parse_file(&args.model_file);
-    println!("{:#?}", def);
+    if let Some(Command::Fmt(args)) = &args.command {
+        return fmt(args);
+    }
+    let model_file = args.model_file.expect("MODELICA_FILE is required");
+    match rumoca_parser::parse_file(&model_file) {
+        Ok(def) => {
+            println!("{:#?}", def);
+            Ok(())
+        }
+        Err(err) => {
+            let writer = StandardStream::stderr(ColorChoice::Always);
+            err.emit(&mut writer.lock())?;
+            process::exit(1);
+        }
+    }
+}
+
+fn fmt(args: &FmtArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let options = FormatOptions {
+        indent_width: args.indent_width,
+        line_width: args.line_width,
+        split_declarations: args.split_declarations,
+        align_descriptions: args.align_descriptions,
+    };
+    let mut files = Vec::new();
+    for path in &args.paths {
+        collect_files(path, &mut files)?;
+    }
+
+    let mut failed = false;
+    for file in &files {
+        let file_name = file.to_string_lossy();
+        let source = fs::read_to_string(file)?;
+        let formatted = match formatter::format(&file_name, &source, &options) {
+            Ok(formatted) => formatted,
+            Err(diagnostics) => {
+                let writer = StandardStream::stderr(ColorChoice::Always);
+                diagnostics.emit(&mut writer.lock())?;
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if args.check {
+            print!("{}", formatter::diff(&file_name, &source, &formatted));
+            failed = true;
+        } else {
+            fs::write(file, formatted)?;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// Collects a file, or the .mo files below a directory in sorted order.
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries: Vec<_> = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "mo") {
+            collect_files(&entry, files)?;
+        }
+    }
     Ok(())
 }
@@ -4,9 +4,31 @@ use crate::s0_lexer::tokens::{LexicalError, Token}; // your Token enum, as above
 
 pub type Spanned<Tok, Loc, Error> = Result<(Loc, Tok, Loc), Error>;
 
+/// A lexical error together with the byte span of the offending input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedLexicalError {
+    pub error: LexicalError,
+    pub span: (usize, usize),
+}
+
+/// The kind of an open bracket.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Bracket {
+    /// The "[" of array subscripts.
+    Subscripts,
+    /// The "[" of a matrix constructor, whose rows are separated by ";".
+    Matrix,
+    /// A "(" or "{".
+    Other,
+}
+
 pub struct Lexer<'input> {
     // instead of an iterator over characters, we have a token iterator
     token_stream: SpannedIter<'input, Token>,
+    // open brackets, innermost last
+    brackets: Vec<Bracket>,
+    // whether a "[" following the previous token opens array subscripts
+    subscriptable: bool,
 }
 
 impl<'input> Lexer<'input> {
@@ -14,17 +36,52 @@ impl<'input> Lexer<'input> {
         // the Token::lexer() method is provided by the Logos trait
         Self {
             token_stream: Token::lexer(input).spanned(),
+            brackets: Vec::new(),
+            subscriptable: false,
+        }
+    }
+
+    /// Tracks the open brackets, and turns `end` inside array subscripts
+    /// into `Token::SubscriptEnd`.
+    fn track_brackets(&mut self, token: Token) -> Token {
+        match token {
+            Token::LBracket if self.subscriptable => self.brackets.push(Bracket::Subscripts),
+            Token::LBracket => self.brackets.push(Bracket::Matrix),
+            Token::LParen | Token::LBrace => self.brackets.push(Bracket::Other),
+            Token::RBracket | Token::RParen | Token::RBrace => {
+                self.brackets.pop();
+            }
+            // only matrix rows are separated by a ";" inside brackets, so
+            // reset after unbalanced input otherwise
+            Token::Semicolon if self.brackets.last() != Some(&Bracket::Matrix) => {
+                self.brackets.clear()
+            }
+            _ => {}
+        }
+        self.subscriptable = matches!(
+            token,
+            Token::Identifier(_) | Token::QIdentifier(_) | Token::RBracket | Token::RParen
+        );
+        if token == Token::KeywordEnd && self.brackets.contains(&Bracket::Subscripts) {
+            Token::SubscriptEnd
+        } else {
+            token
         }
     }
 }
 
 impl Iterator for Lexer<'_> {
-    type Item = Spanned<Token, usize, LexicalError>;
+    type Item = Spanned<Token, usize, SpannedLexicalError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.token_stream
-            .next()
-            .map(|(token, span)| Ok((span.start, token?, span.end)))
+        let (token, span) = self.token_stream.next()?;
+        Some(match token {
+            Ok(token) => Ok((span.start, self.track_brackets(token), span.end)),
+            Err(error) => Err(SpannedLexicalError {
+                error,
+                span: (span.start, span.end),
+            }),
+        })
     }
 }
 
@@ -41,4 +98,98 @@ mod tests {
             println!("{:?}", result)
         }
     }
+
+    #[test]
+    fn test_lexer_string() {
+        let source_code = r#""Mass [kg], über" "say \"hi\"\n" "a
+b""#;
+        let strings: Vec<_> = Lexer::new(source_code)
+            .map(|result| match result.unwrap().1 {
+                Token::String(string) => string,
+                token => panic!("unexpected token {}", token),
+            })
+            .collect();
+        assert_eq!(strings[0].value, "Mass [kg], über");
+        assert_eq!(strings[1].raw, r#"say \"hi\"\n"#);
+        assert_eq!(strings[1].value, "say \"hi\"\n");
+        assert_eq!(strings[2].value, "a\nb");
+    }
+
+    #[test]
+    fn test_lexer_quoted_identifier() {
+        let source_code = r"'my var' 'a.b[1]' 'it\'s' 'end' end";
+        let tokens: Vec<_> = Lexer::new(source_code)
+            .map(|result| result.unwrap().1)
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                Token::QIdentifier("'my var'".to_string()),
+                Token::QIdentifier("'a.b[1]'".to_string()),
+                Token::QIdentifier(r"'it\'s'".to_string()),
+                Token::QIdentifier("'end'".to_string()),
+                Token::KeywordEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_trivia() {
+        use crate::s0_lexer::trivia::{tokenize, SyntaxKind};
+
+        for entry in std::fs::read_dir("tests/models").unwrap() {
+            let source_code = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            let tokens = tokenize(&source_code);
+            let text: String = tokens
+                .iter()
+                .map(|token| token.text(&source_code))
+                .collect();
+            assert_eq!(text, source_code);
+        }
+
+        let source_code = "x /* a */ = 1; // b\n\t'y'";
+        let kinds: Vec<_> = tokenize(source_code)
+            .into_iter()
+            .map(|token| token.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                SyntaxKind::Token(Token::Identifier("x".to_string())),
+                SyntaxKind::Whitespace,
+                SyntaxKind::BlockComment,
+                SyntaxKind::Whitespace,
+                SyntaxKind::Token(Token::Equal),
+                SyntaxKind::Whitespace,
+                SyntaxKind::Token(Token::UnsignedInteger("1".to_string())),
+                SyntaxKind::Token(Token::Semicolon),
+                SyntaxKind::Whitespace,
+                SyntaxKind::LineComment,
+                SyntaxKind::Newline,
+                SyntaxKind::Whitespace,
+                SyntaxKind::Token(Token::QIdentifier("'y'".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_subscript_end() {
+        let source_code =
+            "x[end - 1] = y[f(end), 2:end]; x[size([1, 2; 3, 4], 1):end]; end when; [1, end]";
+        let ends: Vec<_> = Lexer::new(source_code)
+            .map(|result| result.unwrap().1)
+            .filter(|token| matches!(token, Token::KeywordEnd | Token::SubscriptEnd))
+            .collect();
+        assert_eq!(
+            ends,
+            [
+                Token::SubscriptEnd,
+                Token::SubscriptEnd,
+                Token::SubscriptEnd,
+                Token::SubscriptEnd,
+                Token::KeywordEnd,
+                Token::KeywordEnd,
+            ]
+        );
+    }
 }
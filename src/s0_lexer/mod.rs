@@ -1,2 +1,3 @@
 pub mod lexer;
 pub mod tokens;
+pub mod trivia;
@@ -1,8 +1,8 @@
-use logos::{Lexer, Logos, Skip};
+use logos::{Lexer, Logos};
 use std::fmt; // to implement the Display trait later
 use std::num::ParseIntError;
 
-#[derive(Default, Debug, Clone, PartialEq)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub enum LexicalError {
     InvalidInteger(ParseIntError),
     #[default]
@@ -15,16 +15,45 @@ impl From<ParseIntError> for LexicalError {
     }
 }
 
-/// Update the line count and the char index.
-fn newline_callback(lex: &mut Lexer<Token>) -> Skip {
-    lex.extras.0 += 1;
-    lex.extras.1 = lex.span().end;
-    Skip
+/// A string literal as written between the quotes, and with its
+/// escape sequences resolved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StringLiteral {
+    pub raw: String,
+    pub value: String,
 }
 
-fn quoted_string_callback(lex: &mut Lexer<Token>) -> String {
+/// Resolves the S-ESCAPE sequences of a string literal.
+pub fn unescape(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('a') => value.push('\u{07}'),
+            Some('b') => value.push('\u{08}'),
+            Some('f') => value.push('\u{0c}'),
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some('t') => value.push('\t'),
+            Some('v') => value.push('\u{0b}'),
+            Some(c) => value.push(c),
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
+fn quoted_string_callback(lex: &mut Lexer<Token>) -> StringLiteral {
     let slice = lex.slice();
-    slice[1..slice.len() - 1].to_string()
+    let raw = &slice[1..slice.len() - 1];
+    StringLiteral {
+        raw: raw.to_string(),
+        value: unescape(raw),
+    }
 }
 
 // Modelica 3.7-dev
@@ -42,17 +71,22 @@ fn quoted_string_callback(lex: &mut Lexer<Token>) -> String {
 // S-ESCAPE = "\'" | "\"" | "\?" | "\\"
 //    | "\a" | "\b" | "\f" | "\n" | "\r" | "\t" | "\v"
 //
+// 2.3.2 Literal Constants
+// ============================================================================
+// STRING = """ { S-CHAR | S-ESCAPE } """
+// S-CHAR = any member of the Unicode character set except """ and "\"
+//
 // 2.3.3 Modelica Keywords
 // ============================================================================
-#[derive(Logos, Clone, Debug, PartialEq)]
+#[derive(Logos, Clone, Debug, PartialEq, Eq)]
 #[logos(
     skip r"[ \t\f\r]+",
     skip r"//.*\n?",
     skip r"\/\*([^*]|\*[^\/])+\*\/",
-    extras = (usize, usize),
     error = LexicalError)]
 pub enum Token {
-    #[regex(r"\n", newline_callback)]
+    // lines are tracked by the `LineIndex` of the source map
+    #[regex(r"\n", logos::skip)]
     Newline,
 
     #[token("algorithm")]
@@ -91,6 +125,9 @@ pub enum Token {
     KeywordEncapsulated,
     #[token("end")]
     KeywordEnd,
+    /// `end` inside array subscripts, produced by `lexer::Lexer` instead
+    /// of `KeywordEnd`, so that it can be parsed as an expression there.
+    SubscriptEnd,
     #[token("enumeration")]
     KeywordEnumeration,
     #[token("equation")]
@@ -177,8 +214,12 @@ pub enum Token {
     #[regex("[_a-zA-Z][_0-9a-zA-Z]*", |lex| lex.slice().to_string())]
     Identifier(String),
 
-    #[regex("\"[\\- _0-9a-zA-Z]*\"", quoted_string_callback)]
-    String(String),
+    // Note: the quotes are kept, since 'x' and x are distinct identifiers
+    #[regex(r#"'([_0-9a-zA-Z!#$%&()*+,\-./:;<>=?@\[\]^{}|~ "]|\\['"?\\abfnrtv])*'"#, |lex| lex.slice().to_string())]
+    QIdentifier(String),
+
+    #[regex(r#""([^"\\]|\\['"?\\abfnrtv])*""#, quoted_string_callback)]
+    String(StringLiteral),
 
     #[regex("[1-9][0-9]*", |lex| lex.slice().to_string(), priority=3)]
     UnsignedInteger(String),
@@ -0,0 +1,100 @@
+//! A lossless token stream, keeping the whitespace and comments
+//! that the lexer skips.
+//!
+//! Concatenating the text of all tokens gives back the source exactly.
+
+use crate::s0_lexer::lexer::Lexer;
+use crate::s0_lexer::tokens::Token;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SyntaxKind {
+    /// A token seen by the parser.
+    Token(Token),
+    /// Input the lexer could not read.
+    Error,
+    /// Spaces, tabs, form feeds and carriage returns.
+    Whitespace,
+    Newline,
+    /// A `//` comment, without its newline.
+    LineComment,
+    /// A `/* */` comment.
+    BlockComment,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(&self) -> bool {
+        !matches!(self, SyntaxKind::Token(_) | SyntaxKind::Error)
+    }
+
+    pub fn is_comment(&self) -> bool {
+        matches!(self, SyntaxKind::LineComment | SyntaxKind::BlockComment)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntaxToken {
+    pub kind: SyntaxKind,
+    /// The byte span in the source.
+    pub span: (usize, usize),
+}
+
+impl SyntaxToken {
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.span.0..self.span.1]
+    }
+}
+
+/// Splits the source into tokens and trivia, covering every byte.
+pub fn tokenize(source: &str) -> Vec<SyntaxToken> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    for result in Lexer::new(source) {
+        let (kind, start, end) = match result {
+            Ok((start, token, end)) => (SyntaxKind::Token(token), start, end),
+            Err(error) => (SyntaxKind::Error, error.span.0, error.span.1),
+        };
+        push_trivia(source, pos, start, &mut tokens);
+        tokens.push(SyntaxToken {
+            kind,
+            span: (start, end),
+        });
+        pos = end;
+    }
+    push_trivia(source, pos, source.len(), &mut tokens);
+    tokens
+}
+
+/// Splits the text skipped by the lexer between `start` and `end`.
+fn push_trivia(source: &str, start: usize, end: usize, tokens: &mut Vec<SyntaxToken>) {
+    let mut pos = start;
+    while pos < end {
+        let rest = &source[pos..end];
+        let (kind, len) = if rest.starts_with('\n') {
+            (SyntaxKind::Newline, 1)
+        } else if rest.starts_with("//") {
+            (
+                SyntaxKind::LineComment,
+                rest.find('\n').unwrap_or(rest.len()),
+            )
+        } else if let Some(body) = rest.strip_prefix("/*") {
+            let len = body.find("*/").map_or(rest.len(), |i| i + 4);
+            (SyntaxKind::BlockComment, len)
+        } else {
+            let len = rest
+                .find(|c: char| !matches!(c, ' ' | '\t' | '\u{0c}' | '\r'))
+                .unwrap_or(rest.len());
+            if len == 0 {
+                // not produced by the lexer, but keep the stream lossless
+                let len = rest.chars().next().map_or(1, char::len_utf8);
+                (SyntaxKind::Error, len)
+            } else {
+                (SyntaxKind::Whitespace, len)
+            }
+        };
+        tokens.push(SyntaxToken {
+            kind,
+            span: (pos, pos + len),
+        });
+        pos += len;
+    }
+}
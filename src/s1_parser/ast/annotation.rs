@@ -0,0 +1,65 @@
+//! This module implements typed accessors for standard annotations.
+
+use super::node::*;
+use super::part::*;
+
+/// The `experiment` annotation of a class, used to configure simulation runs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Experiment {
+    pub start_time: Option<f64>,
+    pub stop_time: Option<f64>,
+    pub tolerance: Option<f64>,
+    pub interval: Option<f64>,
+}
+
+impl ClassDefinition {
+    /// Returns the `experiment` annotation of the class, if present.
+    ///
+    /// Entries that are missing or are not numeric literals are `None`.
+    pub fn experiment(&self) -> Option<Experiment> {
+        let args = find_class_modification(&self.annotation, "experiment")?;
+        let mut experiment = Experiment::default();
+        for arg in args {
+            if let Argument::Modification(modification) = arg {
+                let value = match &modification.modification {
+                    Some(Modification::Expression(ModExpr::Expression(expr))) => number(expr),
+                    _ => None,
+                };
+                match modification.name.parts.join(".").as_str() {
+                    "StartTime" => experiment.start_time = value,
+                    "StopTime" => experiment.stop_time = value,
+                    "Tolerance" => experiment.tolerance = value,
+                    "Interval" => experiment.interval = value,
+                    _ => {}
+                }
+            }
+        }
+        Some(experiment)
+    }
+}
+
+/// Finds the arguments of the class modification `name(...)` in `args`.
+fn find_class_modification<'a>(args: &'a [Argument], name: &str) -> Option<&'a [Argument]> {
+    args.iter().find_map(|arg| match arg {
+        Argument::Modification(ArgumentModification {
+            name: arg_name,
+            modification: Some(Modification::Class(modification)),
+            ..
+        }) if arg_name.parts == [name] => Some(modification.args.as_slice()),
+        _ => None,
+    })
+}
+
+/// Evaluates a, possibly signed or parenthesized, numeric literal.
+fn number(expr: &Expression) -> Option<f64> {
+    match expr {
+        Expression::UnsignedInteger(val) => val.val.parse().ok(),
+        Expression::UnsignedReal(val) => val.val.parse().ok(),
+        Expression::Unary(unary) => match unary.op {
+            UnaryOp::Negative => number(&unary.rhs).map(|val| -val),
+            UnaryOp::Positive | UnaryOp::Paren => number(&unary.rhs),
+            _ => None,
+        },
+        _ => None,
+    }
+}
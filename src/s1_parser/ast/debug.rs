@@ -27,7 +27,11 @@ macro_rules! impl_debug_for_enum {
 
 impl fmt::Debug for NodeData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "NodeData {{id: {:?}, span: {:?}}}", self.id, self.span)
+        write!(
+            f,
+            "NodeData {{id: {:?}, file_id: {:?}, span: {:?}}}",
+            self.id, self.file_id, self.span
+        )
     }
 }
 
@@ -59,29 +63,50 @@ impl fmt::Debug for TypeSpecifier {
     }
 }
 
+impl_debug_for_enum!(ExtendsArgument {
+    Argument,
+    Inheritance
+});
+
+impl_debug_for_enum!(InheritanceModification { Connect, Ident });
+
 impl_debug_for_enum!(Equation {
     Connect,
+    Error,
     For,
+    FunctionCall,
     If,
     Simple,
+    When,
 });
 
 impl_debug_for_enum!(Statement {
     Assignment,
     Break,
+    Error,
     For,
+    FunctionCall,
+    FunctionCallOutputs,
     If,
     Return,
+    When,
     While,
 });
 
 impl_debug_for_enum!(Expression {
     Array,
+    ArrayComprehension,
     Binary,
     Boolean,
+    End,
     FunctionCall,
+    FunctionPartialApplication,
     If,
+    Matrix,
+    Range,
     Ref,
+    String,
+    Tuple,
     Unary,
     UnsignedInteger,
     UnsignedReal
@@ -124,6 +149,8 @@ impl_debug_for_enum!(Argument {
     Replaceable
 });
 
+impl_debug_for_enum!(ArgumentElement { Class, Component });
+
 impl_debug_for_enum!(Modification { Class, Expression });
 
 impl_debug_for_enum!(ModExpr { Break, Expression });
@@ -15,8 +15,10 @@ pub enum CompositionPart {
     #[default]
     Empty,
     AlgorithmSection(AlgorithmSection),
+    Annotation(Vec<Argument>),
     ElementList(ElementList),
     EquationSection(EquationSection),
+    External(ExternalClause),
 }
 
 #[derive(CommonTraits!, Default)]
@@ -37,6 +39,13 @@ pub struct AlgorithmSection {
     pub statements: Vec<Statement>,
 }
 
+#[derive(CommonTraits!, Default)]
+pub struct ExternalFunctionCall {
+    pub output: Option<ComponentReference>,
+    pub function: String,
+    pub args: Vec<Expression>,
+}
+
 #[derive(CommonTraits!, Default)]
 #[allow(clippy::large_enum_variant)]
 pub enum Element {
@@ -52,8 +61,10 @@ pub enum Element {
 pub enum ClassSpecifier {
     #[default]
     Empty,
+    Der(ClassSpecifierDer),
     Extends(ClassSpecifierExtends),
     Long(ClassSpecifierLong),
+    Short(ClassSpecifierShort),
 }
 
 #[derive(CommonTraits!, Default)]
@@ -73,6 +84,24 @@ pub struct ClassSpecifierExtends {
     pub name_end: String,
 }
 
+#[derive(CommonTraits!, Default)]
+pub struct ClassSpecifierShort {
+    pub name: String,
+    pub base_prefix: Causality,
+    pub type_specifier: Option<TypeSpecifier>,
+    pub array_subscripts: Vec<Subscript>,
+    pub modification: Vec<Argument>,
+    pub enumeration: Option<Enumeration>,
+    pub description: Option<Description>,
+}
+
+#[derive(CommonTraits!, Default)]
+pub struct ClassSpecifierDer {
+    pub name: String,
+    pub derivative: Derivative,
+    pub description: Option<Description>,
+}
+
 #[derive(CommonTraits!, Default)]
 pub struct ClassPrefixes {
     pub is_partial: bool,
@@ -95,6 +124,13 @@ pub struct Declaration {
 // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
 // Expressions
 
+#[derive(CommonTraits!, Default)]
+pub struct FunctionArguments {
+    pub args: Vec<Expression>,
+    pub named_args: Vec<NamedArgument>,
+    pub indices: Vec<ForIndex>,
+}
+
 // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
 // Common
 
@@ -1,4 +1,7 @@
+pub mod annotation;
 pub mod debug;
 pub mod fragment;
 pub mod node;
 pub mod part;
+pub mod printer;
+pub mod visitor;
@@ -1,7 +1,8 @@
 //! This module contains AST nodes.
 //!
 //! Nodes are used are annotated for with span, and id information.
-//! They are meant to be used for visitor patterns and tree annotation.
+//! They are meant to be used for visitor patterns and tree annotation,
+//! see the `visitor` module.
 //! Note that enums are also considered nodes if their variants are all nodes.
 
 use super::part::*;
@@ -28,37 +29,121 @@ pub struct ClassDefinition {
     pub node_data: NodeData,
     pub name: String,
     pub class_type: ClassType,
-    pub extends: Vec<TypeSpecifier>,
+    pub extends: Vec<ExtendsClause>,
     pub imports: Vec<ImportClause>,
     pub flags: ClassFlags,
     pub modification: Vec<Argument>,
     pub description: DescriptionString,
+    pub annotation: Vec<Argument>,
+    pub base_prefix: Causality,
+    pub base_type: Option<TypeSpecifier>,
+    pub array_subscripts: Vec<Subscript>,
+    pub enumeration: Option<Enumeration>,
+    pub derivative: Option<Derivative>,
     pub components: IndexMap<String, ComponentDeclaration>,
     pub classes: IndexMap<String, ClassDefinition>,
     pub equations: Vec<Equation>,
     pub algorithms: Vec<Vec<Statement>>,
     pub initial_equations: Vec<Equation>,
     pub initial_algorithms: Vec<Vec<Statement>>,
+    pub external: Option<ExternalClause>,
+    pub constraining_clause: Option<ConstrainingClause>,
+}
+
+#[derive(CommonTraits!, Default, Debug)]
+pub struct Enumeration {
+    pub node_data: NodeData,
+    /// Set for `enumeration(:)`, where the literals are left unspecified.
+    pub unspecified: bool,
+    pub literals: Vec<EnumerationLiteral>,
+}
+
+#[derive(CommonTraits!, Default, Debug)]
+pub struct EnumerationLiteral {
+    pub node_data: NodeData,
+    pub ident: String,
+    pub description: Option<Description>,
+}
+
+/// A `der(F, x, y)` derivative of the function `F` with respect to `x` and `y`.
+#[derive(CommonTraits!, Default, Debug)]
+pub struct Derivative {
+    pub node_data: NodeData,
+    pub type_specifier: TypeSpecifier,
+    pub idents: Vec<String>,
 }
 
 #[derive(CommonTraits!, Default, Debug)]
 pub struct ImportClause {
     pub node_data: NodeData,
+    pub kind: ImportKind,
     pub alias: String,
     pub name: Name,
+    pub import_list: Vec<String>,
     pub description: Option<Description>,
 }
 
+#[derive(CommonTraits!, Default, Debug)]
+pub struct ExternalClause {
+    pub node_data: NodeData,
+    pub language: Option<String>,
+    pub output: Option<ComponentReference>,
+    pub function: Option<String>,
+    pub args: Vec<Expression>,
+    pub annotation: Vec<Argument>,
+}
+
 #[derive(CommonTraits!, Default, Debug)]
 pub struct ExtendsClause {
     pub node_data: NodeData,
     pub type_specifier: TypeSpecifier,
+    pub modification: Vec<ExtendsArgument>,
+    pub annotation: Vec<Argument>,
+}
+
+#[derive(CommonTraits!, Default)]
+#[allow(clippy::large_enum_variant)]
+pub enum ExtendsArgument {
+    #[default]
+    Empty,
+    Argument(Argument),
+    Inheritance(InheritanceModification),
+}
+
+#[derive(CommonTraits!, Default)]
+pub enum InheritanceModification {
+    #[default]
+    Empty,
+    Connect(InheritanceBreakConnect),
+    Ident(InheritanceBreakIdent),
+}
+
+#[derive(CommonTraits!, Default, Debug)]
+pub struct InheritanceBreakConnect {
+    pub node_data: NodeData,
+    pub lhs: ComponentReference,
+    pub rhs: ComponentReference,
+}
+
+#[derive(CommonTraits!, Default, Debug)]
+pub struct InheritanceBreakIdent {
+    pub node_data: NodeData,
+    pub ident: String,
+}
+
+#[derive(CommonTraits!, Default, Debug)]
+pub struct ConstrainingClause {
+    pub node_data: NodeData,
+    pub type_specifier: TypeSpecifier,
+    pub modification: Vec<Argument>,
+    pub description: Option<Description>,
 }
 
 #[derive(CommonTraits!, Default, Debug)]
 pub struct ComponentDeclaration {
     pub node_data: NodeData,
     pub name: String,
+    pub quoted: bool,
     pub type_specifier: TypeSpecifier,
     pub flags: ElementFlags,
     pub connection: Connection,
@@ -69,6 +154,7 @@ pub struct ComponentDeclaration {
     pub modification: Option<Modification>,
     pub condition_attribute: Option<Expression>,
     pub description: Option<Description>,
+    pub constraining_clause: Option<ConstrainingClause>,
 }
 
 #[derive(CommonTraits!, Default)]
@@ -86,9 +172,19 @@ pub enum Equation {
     #[default]
     Empty,
     Connect(EquationConnect),
+    Error(EquationError),
     For(EquationFor),
+    FunctionCall(EquationFunctionCall),
     If(EquationIf),
     Simple(EquationSimple),
+    When(EquationWhen),
+}
+
+#[derive(CommonTraits!, Default, Debug)]
+pub struct EquationFunctionCall {
+    pub node_data: NodeData,
+    pub call: FunctionCall,
+    pub description: Option<Description>,
 }
 
 #[derive(CommonTraits!, Default, Debug)]
@@ -123,6 +219,19 @@ pub struct EquationConnect {
     pub description: Option<Description>,
 }
 
+#[derive(CommonTraits!, Default, Debug)]
+pub struct EquationWhen {
+    pub node_data: NodeData,
+    pub when_blocks: Vec<EquationWhenBlock>,
+    pub description: Option<Description>,
+}
+
+/// Placeholder for an equation that failed to parse.
+#[derive(CommonTraits!, Default, Debug)]
+pub struct EquationError {
+    pub node_data: NodeData,
+}
+
 #[derive(CommonTraits!, Default, Debug)]
 pub struct EquationIfBlock {
     pub node_data: NodeData,
@@ -130,6 +239,13 @@ pub struct EquationIfBlock {
     pub eqs: Vec<Equation>,
 }
 
+#[derive(CommonTraits!, Default, Debug)]
+pub struct EquationWhenBlock {
+    pub node_data: NodeData,
+    pub cond: Expression,
+    pub eqs: Vec<Equation>,
+}
+
 // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
 // Statements
 
@@ -139,9 +255,13 @@ pub enum Statement {
     Empty,
     Assignment(StatementAssignment),
     Break(StatementBreak),
+    Error(StatementError),
     For(StatementFor),
+    FunctionCall(StatementFunctionCall),
+    FunctionCallOutputs(StatementFunctionCallOutputs),
     If(StatementIf),
     Return(StatementReturn),
+    When(StatementWhen),
     While(StatementWhile),
 }
 
@@ -153,6 +273,23 @@ pub struct StatementAssignment {
     pub description: Option<Description>,
 }
 
+#[derive(CommonTraits!, Default, Debug)]
+pub struct StatementFunctionCall {
+    pub node_data: NodeData,
+    pub call: FunctionCall,
+    pub description: Option<Description>,
+}
+
+/// A `(a, , c) := f(x)` call assigning several function outputs.
+/// Skipped outputs are `None`.
+#[derive(CommonTraits!, Default, Debug)]
+pub struct StatementFunctionCallOutputs {
+    pub node_data: NodeData,
+    pub outputs: Vec<Option<Expression>>,
+    pub call: FunctionCall,
+    pub description: Option<Description>,
+}
+
 #[derive(CommonTraits!, Default, Debug)]
 pub struct StatementIf {
     pub node_data: NodeData,
@@ -177,6 +314,13 @@ pub struct StatementWhile {
     pub description: Option<Description>,
 }
 
+#[derive(CommonTraits!, Default, Debug)]
+pub struct StatementWhen {
+    pub node_data: NodeData,
+    pub when_blocks: Vec<StatementWhenBlock>,
+    pub description: Option<Description>,
+}
+
 #[derive(CommonTraits!, Default, Debug)]
 pub struct StatementBreak {
     pub node_data: NodeData,
@@ -189,6 +333,12 @@ pub struct StatementReturn {
     pub description: Option<Description>,
 }
 
+/// Placeholder for a statement that failed to parse.
+#[derive(CommonTraits!, Default, Debug)]
+pub struct StatementError {
+    pub node_data: NodeData,
+}
+
 #[derive(CommonTraits!, Default, Debug)]
 pub struct StatementIfBlock {
     pub node_data: NodeData,
@@ -196,6 +346,13 @@ pub struct StatementIfBlock {
     pub stmts: Vec<Statement>,
 }
 
+#[derive(CommonTraits!, Default, Debug)]
+pub struct StatementWhenBlock {
+    pub node_data: NodeData,
+    pub cond: Expression,
+    pub stmts: Vec<Statement>,
+}
+
 // ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
 // Expressions
 
@@ -204,11 +361,18 @@ pub enum Expression {
     #[default]
     Empty,
     Array(Array),
+    ArrayComprehension(ArrayComprehension),
     Binary(Binary),
     Boolean(Boolean),
+    End(End),
     FunctionCall(FunctionCall),
+    FunctionPartialApplication(FunctionPartialApplication),
     If(ExpressionIf),
+    Matrix(Matrix),
+    Range(Range),
     Ref(ComponentReference),
+    String(ExpressionString),
+    Tuple(Tuple),
     Unary(Unary),
     UnsignedInteger(UnsignedInteger),
     UnsignedReal(UnsignedReal),
@@ -220,6 +384,33 @@ pub struct Array {
     pub args: Vec<Expression>,
 }
 
+/// A `(a, , c)` output expression list, skipped outputs are `None`.
+#[derive(CommonTraits!, Default, Debug)]
+pub struct Tuple {
+    pub node_data: NodeData,
+    pub args: Vec<Option<Expression>>,
+}
+
+/// A `[a, b; c, d]` matrix constructor, concatenating its rows.
+#[derive(CommonTraits!, Default, Debug)]
+pub struct Matrix {
+    pub node_data: NodeData,
+    pub rows: Vec<MatrixRow>,
+}
+
+#[derive(CommonTraits!, Default, Debug)]
+pub struct MatrixRow {
+    pub node_data: NodeData,
+    pub args: Vec<Expression>,
+}
+
+#[derive(CommonTraits!, Default, Debug)]
+pub struct ArrayComprehension {
+    pub node_data: NodeData,
+    pub expr: Box<Expression>,
+    pub indices: Vec<ForIndex>,
+}
+
 #[derive(CommonTraits!, Default, Debug)]
 pub struct Unary {
     pub node_data: NodeData,
@@ -235,6 +426,15 @@ pub struct Binary {
     pub rhs: Box<Expression>,
 }
 
+/// A `start:end` or `start:step:end` range.
+#[derive(CommonTraits!, Default, Debug)]
+pub struct Range {
+    pub node_data: NodeData,
+    pub start: Box<Expression>,
+    pub step: Option<Box<Expression>>,
+    pub end: Box<Expression>,
+}
+
 #[derive(CommonTraits!, Default, Debug)]
 pub struct ExpressionIf {
     pub node_data: NodeData,
@@ -247,6 +447,23 @@ pub struct FunctionCall {
     pub node_data: NodeData,
     pub comp: ComponentReference,
     pub args: Vec<Expression>,
+    pub named_args: Vec<NamedArgument>,
+    /// Iterators of a reduction such as `sum(x[i] for i in 1:n)`.
+    pub indices: Vec<ForIndex>,
+}
+
+#[derive(CommonTraits!, Default, Debug)]
+pub struct FunctionPartialApplication {
+    pub node_data: NodeData,
+    pub type_specifier: TypeSpecifier,
+    pub named_args: Vec<NamedArgument>,
+}
+
+#[derive(CommonTraits!, Default, Debug)]
+pub struct NamedArgument {
+    pub node_data: NodeData,
+    pub ident: String,
+    pub value: Expression,
 }
 
 #[derive(CommonTraits!, Default, Debug)]
@@ -268,12 +485,27 @@ pub struct UnsignedReal {
     pub val: String,
 }
 
+#[derive(CommonTraits!, Default, Debug)]
+pub struct ExpressionString {
+    pub node_data: NodeData,
+    /// The literal as written between the quotes.
+    pub raw: String,
+    /// The literal with its escape sequences resolved.
+    pub val: String,
+}
+
 #[derive(CommonTraits!, Default, Debug)]
 pub struct Boolean {
     pub node_data: NodeData,
     pub val: bool,
 }
 
+/// The `end` of the subscripted dimension, only found inside array subscripts.
+#[derive(CommonTraits!, Default, Debug)]
+pub struct End {
+    pub node_data: NodeData,
+}
+
 #[derive(CommonTraits!, Default)]
 pub struct ComponentReference {
     pub node_data: NodeData,
@@ -285,6 +517,7 @@ pub struct ComponentReference {
 pub struct RefPart {
     pub node_data: NodeData,
     pub name: String,
+    pub quoted: bool,
     pub array_subscripts: Vec<Subscript>,
 }
 
@@ -305,6 +538,7 @@ pub struct SubscriptRange {
 // Modification
 
 #[derive(CommonTraits!, Default)]
+#[allow(clippy::large_enum_variant)]
 pub enum Argument {
     #[default]
     Empty,
@@ -326,11 +560,28 @@ pub struct ArgumentModification {
 #[derive(CommonTraits!, Default, Debug)]
 pub struct ArgumentRedeclaration {
     pub node_data: NodeData,
+    pub each: bool,
+    pub is_final: bool,
+    pub element: ArgumentElement,
 }
 
 #[derive(CommonTraits!, Default, Debug)]
 pub struct ArgumentReplaceable {
     pub node_data: NodeData,
+    pub each: bool,
+    pub is_final: bool,
+    pub element: ArgumentElement,
+}
+
+/// A short class definition or component clause1 declared in a modification.
+/// Replaceable elements have the replaceable flag and constraining clause set.
+#[derive(CommonTraits!, Default)]
+#[allow(clippy::large_enum_variant)]
+pub enum ArgumentElement {
+    #[default]
+    Empty,
+    Class(ClassDefinition),
+    Component(ComponentDeclaration),
 }
 
 #[derive(CommonTraits!, Default)]
@@ -2,15 +2,22 @@
 //!
 //! Parts are not considered nodes, but they are contained in the
 //! final AST, unlike fragments.
+use crate::s0_lexer::lexer::SpannedLexicalError;
+use crate::s0_lexer::tokens::Token;
+use lalrpop_util::ErrorRecovery;
 use serde::{Deserialize, Serialize};
 
 derive_alias! {
     #[derive(CommonTraits!)] = #[derive(Clone, PartialEq, Eq, Serialize, Deserialize)];
 }
 
-#[derive(CommonTraits!, Default)]
+#[derive(Default)]
 pub struct ParserContext {
     id_count: usize,
+    /// The file id recorded in the `NodeData` of every node.
+    pub file_id: usize,
+    /// Errors the parser recovered from, in source order.
+    pub errors: Vec<ErrorRecovery<usize, Token, SpannedLexicalError>>,
 }
 
 impl ParserContext {
@@ -27,13 +34,17 @@ impl ParserContext {
 #[derive(CommonTraits!, Default)]
 pub struct NodeData {
     pub id: usize,
+    /// The file of the node, as added to the `SourceMap`.
+    pub file_id: usize,
+    /// The byte span in the file.
     pub span: (usize, usize),
 }
 
 impl NodeData {
-    pub fn new(id: usize, left: usize, right: usize) -> Self {
+    pub fn new(id: usize, file_id: usize, left: usize, right: usize) -> Self {
         NodeData {
             id,
+            file_id,
             span: (left, right),
         }
     }
@@ -117,6 +128,20 @@ pub enum Connection {
     Stream,
 }
 
+#[derive(CommonTraits!, Default, Debug)]
+pub enum ImportKind {
+    #[default]
+    Empty,
+    /// `import A.B.C;`
+    Qualified,
+    /// `import D = A.B.C;`
+    Renaming,
+    /// `import A.B.*;`
+    Unqualified,
+    /// `import A.B.{C, D};`
+    Multiple,
+}
+
 #[derive(CommonTraits!, Default, Debug)]
 pub enum UnaryOp {
     #[default]
@@ -152,7 +177,6 @@ pub enum BinaryOp {
     NotEqual,
     Or,
     Paren,
-    Range,
     Sub,
 }
 
@@ -184,4 +208,24 @@ pub struct DescriptionString {
 #[derive(CommonTraits!, Default)]
 pub struct Name {
     pub parts: Vec<String>,
+    /// Whether each part is a quoted identifier.
+    pub quoted: Vec<bool>,
+}
+
+impl Name {
+    pub fn new(ident: String) -> Self {
+        let mut name = Name::default();
+        name.push(ident);
+        name
+    }
+
+    pub fn push(&mut self, ident: String) {
+        self.quoted.push(is_quoted(&ident));
+        self.parts.push(ident);
+    }
+}
+
+/// Returns true for a quoted identifier such as `'my var'`.
+pub fn is_quoted(ident: &str) -> bool {
+    ident.starts_with('\'')
 }
@@ -0,0 +1,1312 @@
+//! This module regenerates Modelica source from the AST.
+//!
+//! Expressions are only parenthesized where the grammar requires it,
+//! parentheses written in the source are kept by `UnaryOp::Paren`.
+//! Sections are printed in a fixed order, so the output parses back to
+//! the same AST, but not necessarily to the same layout.
+//!
+//! Given the `Cst` of the source, the comments and blank lines around
+//! elements, equations and statements are printed as well, which is what
+//! the formatter builds on.
+
+use super::node::*;
+use super::part::*;
+use crate::s0_lexer::tokens::Token;
+use crate::s0_lexer::trivia::{SyntaxKind, SyntaxToken};
+use crate::s1_parser::cst::Cst;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+// Precedence levels of the expression grammar, a lower level binds tighter.
+const PRIMARY: u8 = 1;
+const EXP: u8 = 3;
+const MUL: u8 = 4;
+const ADD: u8 = 5;
+const RELATION: u8 = 7;
+const NOT: u8 = 8;
+const AND: u8 = 9;
+const OR: u8 = 10;
+const RANGE: u8 = 11;
+const EXPRESSION: u8 = 12;
+
+/// Layout options of the printer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FormatOptions {
+    /// Spaces per indentation level.
+    pub indent_width: usize,
+    /// Longer lines are wrapped after a comma where possible.
+    pub line_width: usize,
+    /// Whether to print each component of a clause such as `Real a, b;`
+    /// on its own line. Clauses can only be kept together given the `Cst`.
+    pub split_declarations: bool,
+    /// Whether to align the descriptions of consecutive components.
+    pub align_descriptions: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent_width: 4,
+            line_width: 100,
+            split_declarations: true,
+            align_descriptions: true,
+        }
+    }
+}
+
+/// Prints a stored definition as Modelica source.
+pub fn print(def: &StoredDefinition) -> String {
+    print_with(def, &FormatOptions::default(), None)
+}
+
+/// Prints a stored definition with the given options, keeping the comments
+/// of the source if its `Cst` is given.
+pub fn print_with(def: &StoredDefinition, options: &FormatOptions, cst: Option<&Cst>) -> String {
+    let mut printer = Printer::new(options, cst);
+    printer.stored_definition(def);
+    printer.out
+}
+
+/// Prints a class definition as Modelica source.
+pub fn print_class_definition(def: &ClassDefinition) -> String {
+    let options = FormatOptions::default();
+    let mut printer = Printer::new(&options, None);
+    printer.class_definition(def);
+    printer.out
+}
+
+/// Prints an expression on a single line.
+pub fn print_expression(expr: &Expression) -> String {
+    expression(expr, EXPRESSION)
+}
+
+struct Printer<'a> {
+    out: String,
+    depth: usize,
+    options: &'a FormatOptions,
+    cst: Option<&'a Cst<'a>>,
+    /// Whether the last printed line opens a block, which is not followed
+    /// by a blank line.
+    opened: bool,
+    /// Start offsets of the comments and blank lines of the source that
+    /// were printed already.
+    printed: HashSet<usize>,
+}
+
+/// A line of a component clause, split before the description of its
+/// last component so that descriptions can be aligned.
+struct ComponentLine<'c> {
+    comps: Vec<&'c ComponentDeclaration>,
+    head: String,
+    description: String,
+    constraining_clause: String,
+}
+
+impl<'a> Printer<'a> {
+    fn new(options: &'a FormatOptions, cst: Option<&'a Cst<'a>>) -> Self {
+        Printer {
+            out: String::new(),
+            depth: 0,
+            options,
+            cst,
+            opened: false,
+            printed: HashSet::new(),
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        let indent = self.depth * self.options.indent_width;
+        for (i, line) in wrap(text, indent, self.options).into_iter().enumerate() {
+            let indent = if i == 0 {
+                indent
+            } else {
+                indent + self.options.indent_width
+            };
+            self.out.push_str(&" ".repeat(indent));
+            self.out.push_str(line);
+            self.out.push('\n');
+        }
+        self.opened = false;
+    }
+
+    fn stored_definition(&mut self, def: &StoredDefinition) {
+        if let Some(within) = &def.within {
+            let start = self
+                .cst
+                .and_then(|cst| cst.tokens.iter().find(|token| !token.kind.is_trivia()))
+                .map_or(0, |token| token.span.0);
+            self.flush_comments(0, start);
+            self.line(&format!("within {};", name(within)));
+            self.header_comments(start);
+        }
+        for (i, class) in def.classes.values().enumerate() {
+            if i > 0 || def.within.is_some() {
+                self.out.push('\n');
+            }
+            self.class_definition(class);
+        }
+        self.flush_comments(0, usize::MAX);
+    }
+
+    fn class_definition(&mut self, def: &ClassDefinition) {
+        self.leading_comments(&def.node_data);
+        let prefix = class_prefix(def);
+        let constraining_clause = constraining_clause(&def.constraining_clause);
+        if is_short_class(def) {
+            self.line(&format!(
+                "{}{}{};",
+                prefix,
+                short_class_specifier(def),
+                constraining_clause
+            ));
+            self.trailing_comments(&def.node_data);
+            return;
+        }
+
+        let description = description_string(&def.description.parts);
+        if def.modification.is_empty() {
+            self.line(&format!("{}{}{}", prefix, def.name, description));
+        } else {
+            self.line(&format!(
+                "{}extends {}({}){}",
+                prefix,
+                def.name,
+                arguments(&def.modification),
+                description
+            ));
+        }
+        self.header_comments(def.node_data.span.0);
+        self.opened = true;
+
+        self.depth += 1;
+        for import in &def.imports {
+            self.leading_comments(&import.node_data);
+            self.line(&format!("{};", import_clause(import)));
+            self.trailing_comments(&import.node_data);
+        }
+        for extends in &def.extends {
+            self.leading_comments(&extends.node_data);
+            self.line(&format!("{};", extends_clause(extends)));
+            self.trailing_comments(&extends.node_data);
+        }
+        for class in def.classes.values() {
+            self.class_definition(class);
+        }
+        let (protected, public): (Vec<_>, Vec<_>) = def
+            .components
+            .values()
+            .partition(|comp| comp.visibility == Visibility::Protected);
+        self.components(&public);
+        if let Some(first) = protected.first() {
+            self.flush_before(def, Some(&first.node_data));
+            self.section("protected");
+            self.components(&protected);
+        }
+        if let Some(first) = def.equations.first() {
+            self.flush_before(def, equation_data(first));
+            self.section("equation");
+            self.equations(&def.equations);
+        }
+        for statements in &def.algorithms {
+            self.flush_before(def, statements.first().and_then(statement_data));
+            self.section("algorithm");
+            self.statements(statements);
+        }
+        if let Some(first) = def.initial_equations.first() {
+            self.flush_before(def, equation_data(first));
+            self.section("initial equation");
+            self.equations(&def.initial_equations);
+        }
+        for statements in &def.initial_algorithms {
+            self.flush_before(def, statements.first().and_then(statement_data));
+            self.section("initial algorithm");
+            self.statements(statements);
+        }
+        if let Some(external) = &def.external {
+            self.flush_before(def, Some(&external.node_data));
+            self.section(&format!("{};", external_clause(external)));
+            self.trailing_comments(&external.node_data);
+        }
+        if !def.annotation.is_empty() {
+            let first = def.annotation.first().and_then(argument_data);
+            self.flush_before(def, first);
+            if let Some(node_data) = first {
+                self.leading_comments(node_data);
+            }
+            self.line(&format!("annotation({});", arguments(&def.annotation)));
+            if let Some(node_data) = def.annotation.last().and_then(argument_data) {
+                self.trailing_comments(node_data);
+            }
+        }
+        self.flush_comments(def.node_data.span.0, def.node_data.span.1);
+        self.depth -= 1;
+        self.line(&format!("end {}{};", def.name, constraining_clause));
+        self.trailing_comments(&def.node_data);
+    }
+
+    /// Prints a section keyword, one level less indented than its contents.
+    fn section(&mut self, keyword: &str) {
+        self.depth -= 1;
+        self.line(keyword);
+        self.depth += 1;
+        self.opened = true;
+    }
+
+    /// Prints component clauses, aligning the descriptions of each run of
+    /// lines not separated by a blank line.
+    fn components(&mut self, comps: &[&ComponentDeclaration]) {
+        let lines = self.component_lines(comps);
+        let mut start = 0;
+        for end in 1..=lines.len() {
+            if end < lines.len()
+                && !self.blank_line_between(
+                    lines[end - 1].comps[lines[end - 1].comps.len() - 1],
+                    lines[end].comps[0],
+                )
+            {
+                continue;
+            }
+            let column = lines[start..end]
+                .iter()
+                .filter(|line| !line.description.is_empty())
+                .map(|line| line.head.chars().count())
+                .max()
+                .unwrap_or(0);
+            for line in &lines[start..end] {
+                for comp in &line.comps {
+                    self.leading_comments(&comp.node_data);
+                }
+                let head = if self.options.align_descriptions && !line.description.is_empty() {
+                    format!("{:width$}", line.head, width = column)
+                } else {
+                    line.head.clone()
+                };
+                self.line(&format!(
+                    "{}{}{};",
+                    head, line.description, line.constraining_clause
+                ));
+                for comp in &line.comps {
+                    self.trailing_comments(&comp.node_data);
+                }
+            }
+            start = end;
+        }
+    }
+
+    /// Groups the components into lines, keeping the components of a
+    /// clause together unless declarations are split.
+    fn component_lines<'c>(&self, comps: &[&'c ComponentDeclaration]) -> Vec<ComponentLine<'c>> {
+        let mut lines: Vec<ComponentLine> = Vec::new();
+        for &comp in comps {
+            match lines.last_mut() {
+                Some(line)
+                    if !self.options.split_declarations
+                        && self.same_clause(line.comps[line.comps.len() - 1], comp) =>
+                {
+                    line.head.push_str(&line.description);
+                    line.head.push_str(", ");
+                    line.head.push_str(&declaration(comp));
+                    line.description = description(&comp.description);
+                    line.comps.push(comp);
+                }
+                _ => lines.push(ComponentLine {
+                    comps: vec![comp],
+                    head: element_prefixes(&comp.flags) + &type_prefix(comp) + &declaration(comp),
+                    description: description(&comp.description),
+                    constraining_clause: constraining_clause(&comp.constraining_clause),
+                }),
+            }
+        }
+        lines
+    }
+
+    /// Whether two components were declared in the same clause, only
+    /// separated by a comma.
+    fn same_clause(&self, prev: &ComponentDeclaration, comp: &ComponentDeclaration) -> bool {
+        let Some(cst) = self.cst else {
+            return false;
+        };
+        let first = cst
+            .tokens
+            .partition_point(|token| token.span.0 < prev.node_data.span.1);
+        let last = cst
+            .tokens
+            .partition_point(|token| token.span.0 < comp.node_data.span.0);
+        let mut tokens = cst.tokens[first..last.max(first)]
+            .iter()
+            .filter(|token| !token.kind.is_trivia());
+        matches!(
+            (tokens.next(), tokens.next()),
+            (Some(token), None) if token.kind == SyntaxKind::Token(Token::Comma)
+        )
+    }
+
+    fn equations(&mut self, eqs: &[Equation]) {
+        for eq in eqs {
+            self.equation(eq);
+        }
+    }
+
+    fn block<T>(&mut self, items: &[T], print: fn(&mut Self, &T)) {
+        self.depth += 1;
+        self.opened = true;
+        for item in items {
+            print(self, item);
+        }
+        self.depth -= 1;
+    }
+
+    fn equation(&mut self, eq: &Equation) {
+        if let Some(node_data) = equation_data(eq) {
+            self.leading_comments(node_data);
+        }
+        match eq {
+            Equation::Simple(eq) => self.line(&format!(
+                "{} = {}{};",
+                expression(&eq.lhs, RANGE),
+                print_expression(&eq.rhs),
+                description(&eq.description)
+            )),
+            Equation::If(eq) => {
+                for (i, block) in eq.if_blocks.iter().enumerate() {
+                    let keyword = if i == 0 { "if" } else { "elseif" };
+                    self.line(&format!(
+                        "{} {} then",
+                        keyword,
+                        print_expression(&block.cond)
+                    ));
+                    if i == 0 {
+                        self.header_comments(eq.node_data.span.0);
+                    }
+                    self.block(&block.eqs, Self::equation);
+                }
+                if !eq.else_eqs.is_empty() {
+                    self.line("else");
+                    self.block(&eq.else_eqs, Self::equation);
+                }
+                self.line(&format!("end if{};", description(&eq.description)));
+            }
+            Equation::For(eq) => {
+                self.line(&format!("for {} loop", for_indices(&eq.indices)));
+                self.header_comments(eq.node_data.span.0);
+                self.block(&eq.eqs, Self::equation);
+                self.line(&format!("end for{};", description(&eq.description)));
+            }
+            Equation::Connect(eq) => self.line(&format!(
+                "connect({}, {}){};",
+                component_reference(&eq.lhs),
+                component_reference(&eq.rhs),
+                description(&eq.description)
+            )),
+            Equation::When(eq) => {
+                for (i, block) in eq.when_blocks.iter().enumerate() {
+                    let keyword = if i == 0 { "when" } else { "elsewhen" };
+                    self.line(&format!(
+                        "{} {} then",
+                        keyword,
+                        print_expression(&block.cond)
+                    ));
+                    if i == 0 {
+                        self.header_comments(eq.node_data.span.0);
+                    }
+                    self.block(&block.eqs, Self::equation);
+                }
+                self.line(&format!("end when{};", description(&eq.description)));
+            }
+            Equation::FunctionCall(eq) => self.line(&format!(
+                "{}{};",
+                function_call(&eq.call),
+                description(&eq.description)
+            )),
+            Equation::Error(_) | Equation::Empty => {}
+        }
+        if let Some(node_data) = equation_data(eq) {
+            self.trailing_comments(node_data);
+        }
+    }
+
+    fn statements(&mut self, stmts: &[Statement]) {
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        if let Some(node_data) = statement_data(stmt) {
+            self.leading_comments(node_data);
+        }
+        match stmt {
+            Statement::Assignment(stmt) => self.line(&format!(
+                "{} := {}{};",
+                component_reference(&stmt.comp),
+                print_expression(&stmt.rhs),
+                description(&stmt.description)
+            )),
+            Statement::FunctionCall(stmt) => self.line(&format!(
+                "{}{};",
+                function_call(&stmt.call),
+                description(&stmt.description)
+            )),
+            Statement::FunctionCallOutputs(stmt) => self.line(&format!(
+                "({}) := {}{};",
+                output_expressions(&stmt.outputs),
+                function_call(&stmt.call),
+                description(&stmt.description)
+            )),
+            Statement::If(stmt) => {
+                for (i, block) in stmt.if_blocks.iter().enumerate() {
+                    let keyword = if i == 0 { "if" } else { "elseif" };
+                    self.line(&format!(
+                        "{} {} then",
+                        keyword,
+                        print_expression(&block.cond)
+                    ));
+                    if i == 0 {
+                        self.header_comments(stmt.node_data.span.0);
+                    }
+                    self.block(&block.stmts, Self::statement);
+                }
+                if !stmt.else_stmts.is_empty() {
+                    self.line("else");
+                    self.block(&stmt.else_stmts, Self::statement);
+                }
+                self.line(&format!("end if{};", description(&stmt.description)));
+            }
+            Statement::For(stmt) => {
+                self.line(&format!("for {} loop", for_indices(&stmt.indices)));
+                self.header_comments(stmt.node_data.span.0);
+                self.block(&stmt.stmts, Self::statement);
+                self.line(&format!("end for{};", description(&stmt.description)));
+            }
+            Statement::While(stmt) => {
+                self.line(&format!("while {} loop", print_expression(&stmt.cond)));
+                self.header_comments(stmt.node_data.span.0);
+                self.block(&stmt.stmts, Self::statement);
+                self.line(&format!("end while{};", description(&stmt.description)));
+            }
+            Statement::When(stmt) => {
+                for (i, block) in stmt.when_blocks.iter().enumerate() {
+                    let keyword = if i == 0 { "when" } else { "elsewhen" };
+                    self.line(&format!(
+                        "{} {} then",
+                        keyword,
+                        print_expression(&block.cond)
+                    ));
+                    if i == 0 {
+                        self.header_comments(stmt.node_data.span.0);
+                    }
+                    self.block(&block.stmts, Self::statement);
+                }
+                self.line(&format!("end when{};", description(&stmt.description)));
+            }
+            Statement::Break(stmt) => {
+                self.line(&format!("break{};", description(&stmt.description)))
+            }
+            Statement::Return(stmt) => {
+                self.line(&format!("return{};", description(&stmt.description)))
+            }
+            Statement::Error(_) | Statement::Empty => {}
+        }
+        if let Some(node_data) = statement_data(stmt) {
+            self.trailing_comments(node_data);
+        }
+    }
+
+    // Comments are only printed given the `Cst`, each of them once.
+
+    /// Prints the comments and blank lines above a node.
+    fn leading_comments(&mut self, node_data: &NodeData) {
+        let Some(cst) = self.cst else {
+            return;
+        };
+        let trivia = cst.leading_trivia(node_data);
+        for (i, token) in trivia.iter().enumerate() {
+            if token.kind.is_comment() {
+                self.comment_line(token);
+            } else if ends_blank_line(trivia, i)
+                && self.printed.insert(token.span.0)
+                && !self.opened
+                && !self.out.is_empty()
+                && !self.out.ends_with("\n\n")
+            {
+                self.out.push('\n');
+            }
+        }
+    }
+
+    /// Whether a blank line separates two components in the source.
+    fn blank_line_between(&self, prev: &ComponentDeclaration, comp: &ComponentDeclaration) -> bool {
+        let Some(cst) = self.cst else {
+            return false;
+        };
+        // components on the same line share their leading trivia
+        if cst.full_span(&prev.node_data).0 == cst.full_span(&comp.node_data).0 {
+            return false;
+        }
+        let trivia = cst.leading_trivia(&comp.node_data);
+        (0..trivia.len()).any(|i| ends_blank_line(trivia, i))
+    }
+
+    /// Appends the comments within a node and at the end of its last line
+    /// to the last printed line.
+    fn trailing_comments(&mut self, node_data: &NodeData) {
+        let Some(cst) = self.cst else {
+            return;
+        };
+        for token in cst
+            .tokens(node_data)
+            .iter()
+            .chain(cst.trailing_trivia(node_data))
+        {
+            self.trailing_comment(token);
+        }
+    }
+
+    /// Appends the comments on the source line of `start`, such as after
+    /// the header of a class, to the last printed line.
+    fn header_comments(&mut self, start: usize) {
+        let Some(cst) = self.cst else {
+            return;
+        };
+        let first = cst.tokens.partition_point(|token| token.span.0 < start);
+        for token in cst.tokens[first..]
+            .iter()
+            .take_while(|token| token.kind != SyntaxKind::Newline)
+        {
+            self.trailing_comment(token);
+        }
+    }
+
+    /// Prints the comments between `start` and `end` that are not attached
+    /// to a printed node.
+    fn flush_comments(&mut self, start: usize, end: usize) {
+        let Some(cst) = self.cst else {
+            return;
+        };
+        for token in &cst.tokens {
+            if token.kind.is_comment() && start <= token.span.0 && token.span.0 < end {
+                self.comment_line(token);
+            }
+        }
+    }
+
+    /// Prints the comments of a class above a node and its leading comments,
+    /// before the section of that node.
+    fn flush_before(&mut self, def: &ClassDefinition, node_data: Option<&NodeData>) {
+        if let (Some(cst), Some(node_data)) = (self.cst, node_data) {
+            self.flush_comments(def.node_data.span.0, cst.full_span(node_data).0);
+        }
+    }
+
+    fn comment_line(&mut self, token: &SyntaxToken) {
+        if let Some(cst) = self.cst {
+            if self.printed.insert(token.span.0) {
+                self.out
+                    .push_str(&" ".repeat(self.depth * self.options.indent_width));
+                self.out.push_str(token.text(cst.source));
+                self.out.push('\n');
+                self.opened = false;
+            }
+        }
+    }
+
+    fn trailing_comment(&mut self, token: &SyntaxToken) {
+        if let Some(cst) = self.cst {
+            if token.kind.is_comment() && self.printed.insert(token.span.0) {
+                self.out.pop();
+                self.out.push(' ');
+                self.out.push_str(token.text(cst.source));
+                self.out.push('\n');
+            }
+        }
+    }
+}
+
+/// Whether the trivia token at `i` ends a line holding only whitespace.
+fn ends_blank_line(trivia: &[SyntaxToken], i: usize) -> bool {
+    trivia[i].kind == SyntaxKind::Newline
+        && trivia[..i]
+            .iter()
+            .rev()
+            .take_while(|token| token.kind != SyntaxKind::Newline)
+            .all(|token| token.kind == SyntaxKind::Whitespace)
+}
+
+/// Splits a line that does not fit the line width at the spaces after
+/// commas, and before a trailing annotation. The outermost break that
+/// fits is taken, or else the first one.
+fn wrap<'t>(text: &'t str, indent: usize, options: &FormatOptions) -> Vec<&'t str> {
+    let mut breaks = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            ' ' if text[..i].ends_with(',') => breaks.push((i, depth)),
+            ' ' if depth == 0 && text[i + 1..].starts_with("annotation(") => {
+                breaks.push((i, depth))
+            }
+            _ => {}
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut width = options.line_width.saturating_sub(indent);
+    loop {
+        let rest = &text[start..];
+        if rest.chars().count() <= width {
+            lines.push(rest);
+            return lines;
+        }
+        let candidates = breaks.iter().filter(|(i, _)| *i > start);
+        let chosen = candidates
+            .clone()
+            .filter(|(i, _)| text[start..*i].chars().count() <= width)
+            .min_by_key(|(i, depth)| (*depth, Reverse(*i)))
+            .or_else(|| candidates.min_by_key(|(i, _)| *i));
+        match chosen {
+            Some(&(i, _)) => {
+                lines.push(&text[start..i]);
+                start = i + 1;
+            }
+            None => {
+                lines.push(rest);
+                return lines;
+            }
+        }
+        width = options
+            .line_width
+            .saturating_sub(indent + options.indent_width);
+    }
+}
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+// Class Level
+
+fn is_short_class(def: &ClassDefinition) -> bool {
+    def.base_type.is_some() || def.enumeration.is_some() || def.derivative.is_some()
+}
+
+/// The element prefixes and class prefixes, followed by a space.
+fn class_prefix(def: &ClassDefinition) -> String {
+    let flags = [
+        ("redeclare ", def.flags.redeclare),
+        ("final ", def.flags.is_final),
+        ("inner ", def.flags.inner),
+        ("outer ", def.flags.outer),
+        ("replaceable ", def.flags.replaceable),
+        ("encapsulated ", def.flags.encapsulated),
+    ];
+    let mut prefix: String = flags
+        .iter()
+        .filter(|(_, set)| *set)
+        .map(|(keyword, _)| *keyword)
+        .collect();
+    prefix.push_str(&class_prefixes(def));
+    prefix
+}
+
+/// The `partial` flag and class type, followed by a space.
+fn class_prefixes(def: &ClassDefinition) -> String {
+    let class_type = match def.class_type {
+        ClassType::Block => "block",
+        ClassType::Connector => "connector",
+        ClassType::ExpandableConnector => "expandable connector",
+        ClassType::Function => "function",
+        ClassType::ImpureFunction => "impure function",
+        ClassType::Model => "model",
+        ClassType::Operator => "operator",
+        ClassType::OperatorFunction => "operator function",
+        ClassType::OperatorRecord => "operator record",
+        ClassType::Package => "package",
+        ClassType::PureFunction => "pure function",
+        ClassType::Record => "record",
+        ClassType::Type => "type",
+        ClassType::Class | ClassType::Empty => "class",
+    };
+    if def.flags.partial {
+        format!("partial {} ", class_type)
+    } else {
+        format!("{} ", class_type)
+    }
+}
+
+fn short_class_specifier(def: &ClassDefinition) -> String {
+    let mut spec = format!("{} = ", def.name);
+    if let Some(derivative) = &def.derivative {
+        spec.push_str(&format!(
+            "der({}, {})",
+            type_specifier(&derivative.type_specifier),
+            derivative.idents.join(", ")
+        ));
+    } else if let Some(enumeration) = &def.enumeration {
+        if enumeration.unspecified {
+            spec.push_str("enumeration(:)");
+        } else {
+            let literals: Vec<_> = enumeration
+                .literals
+                .iter()
+                .map(|literal| format!("{}{}", literal.ident, description(&literal.description)))
+                .collect();
+            spec.push_str(&format!("enumeration({})", literals.join(", ")));
+        }
+    } else if let Some(base_type) = &def.base_type {
+        spec.push_str(causality(&def.base_prefix));
+        spec.push_str(&type_specifier(base_type));
+        spec.push_str(&array_subscripts(&def.array_subscripts));
+        if !def.modification.is_empty() {
+            spec.push_str(&format!("({})", arguments(&def.modification)));
+        }
+    }
+    spec.push_str(&description_string(&def.description.parts));
+    spec.push_str(&annotation_clause(&def.annotation));
+    spec
+}
+
+fn import_clause(import: &ImportClause) -> String {
+    let clause = match import.kind {
+        ImportKind::Renaming => format!("import {} = {}", import.alias, name(&import.name)),
+        ImportKind::Unqualified => format!("import {}.*", name(&import.name)),
+        ImportKind::Multiple => format!(
+            "import {}.{{{}}}",
+            name(&import.name),
+            import.import_list.join(", ")
+        ),
+        ImportKind::Qualified | ImportKind::Empty => format!("import {}", name(&import.name)),
+    };
+    clause + &description(&import.description)
+}
+
+fn extends_clause(extends: &ExtendsClause) -> String {
+    let mut clause = format!("extends {}", type_specifier(&extends.type_specifier));
+    if !extends.modification.is_empty() {
+        let args: Vec<_> = extends
+            .modification
+            .iter()
+            .map(|arg| match arg {
+                ExtendsArgument::Argument(arg) => argument(arg),
+                ExtendsArgument::Inheritance(InheritanceModification::Connect(connect)) => {
+                    format!(
+                        "break connect({}, {})",
+                        component_reference(&connect.lhs),
+                        component_reference(&connect.rhs)
+                    )
+                }
+                ExtendsArgument::Inheritance(InheritanceModification::Ident(ident)) => {
+                    format!("break {}", ident.ident)
+                }
+                ExtendsArgument::Inheritance(InheritanceModification::Empty)
+                | ExtendsArgument::Empty => String::new(),
+            })
+            .collect();
+        clause.push_str(&format!("({})", args.join(", ")));
+    }
+    clause + &annotation_clause(&extends.annotation)
+}
+
+fn constraining_clause(clause: &Option<ConstrainingClause>) -> String {
+    match clause {
+        Some(clause) => {
+            let mut text = format!(" constrainedby {}", type_specifier(&clause.type_specifier));
+            if !clause.modification.is_empty() {
+                text.push_str(&format!("({})", arguments(&clause.modification)));
+            }
+            text + &description(&clause.description)
+        }
+        None => String::new(),
+    }
+}
+
+fn external_clause(external: &ExternalClause) -> String {
+    let mut clause = String::from("external");
+    if let Some(language) = &external.language {
+        clause.push_str(&format!(" {}", string(language)));
+    }
+    if let Some(function) = &external.function {
+        clause.push(' ');
+        if let Some(output) = &external.output {
+            clause.push_str(&format!("{} = ", component_reference(output)));
+        }
+        clause.push_str(&format!("{}({})", function, expressions(&external.args)));
+    }
+    clause + &annotation_clause(&external.annotation)
+}
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+// Components
+
+/// The element prefixes of a component, followed by a space.
+fn element_prefixes(flags: &ElementFlags) -> String {
+    let flags = [
+        ("redeclare ", flags.redeclare),
+        ("final ", flags.is_final),
+        ("inner ", flags.inner),
+        ("outer ", flags.outer),
+        ("replaceable ", flags.replaceable),
+    ];
+    flags
+        .iter()
+        .filter(|(_, set)| *set)
+        .map(|(keyword, _)| *keyword)
+        .collect()
+}
+
+/// The type prefix and type specifier of a component, followed by a space.
+fn type_prefix(comp: &ComponentDeclaration) -> String {
+    let mut prefix = String::new();
+    prefix.push_str(match comp.connection {
+        Connection::Flow => "flow ",
+        Connection::Stream => "stream ",
+        Connection::Empty => "",
+    });
+    prefix.push_str(match comp.variability {
+        Variability::Constant => "constant ",
+        Variability::Discrete => "discrete ",
+        Variability::Parameter => "parameter ",
+        Variability::Continuous | Variability::Empty => "",
+    });
+    prefix.push_str(causality(&comp.causality));
+    prefix.push_str(&type_specifier(&comp.type_specifier));
+    prefix.push(' ');
+    prefix
+}
+
+/// A component declaration without its description.
+fn declaration(comp: &ComponentDeclaration) -> String {
+    let mut text = format!("{}{}", comp.name, array_subscripts(&comp.array_subscripts));
+    if let Some(modification) = &comp.modification {
+        text.push_str(&self::modification(modification));
+    }
+    if let Some(cond) = &comp.condition_attribute {
+        text.push_str(&format!(" if {}", print_expression(cond)));
+    }
+    text
+}
+
+/// A component without element prefixes, as declared in a modification.
+fn component_clause1(comp: &ComponentDeclaration) -> String {
+    type_prefix(comp) + &declaration(comp) + &description(&comp.description)
+}
+
+fn causality(causality: &Causality) -> &'static str {
+    match causality {
+        Causality::Input => "input ",
+        Causality::Output => "output ",
+        Causality::Empty => "",
+    }
+}
+
+fn type_specifier(spec: &TypeSpecifier) -> String {
+    if spec.local {
+        format!(".{}", name(&spec.name))
+    } else {
+        name(&spec.name)
+    }
+}
+
+fn name(name: &Name) -> String {
+    name.parts.join(".")
+}
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+// Modification
+
+fn modification(modification: &Modification) -> String {
+    match modification {
+        Modification::Class(class) => {
+            let mut text = format!("({})", arguments(&class.args));
+            if let Some(expr) = &class.expr {
+                text.push_str(&format!(" = {}", mod_expr(expr)));
+            }
+            text
+        }
+        Modification::Expression(expr) => format!(" = {}", mod_expr(expr)),
+        Modification::Empty => String::new(),
+    }
+}
+
+fn mod_expr(expr: &ModExpr) -> String {
+    match expr {
+        ModExpr::Expression(expr) => print_expression(expr),
+        ModExpr::Break(_) => String::from("break"),
+        ModExpr::Empty => String::new(),
+    }
+}
+
+fn arguments(args: &[Argument]) -> String {
+    args.iter().map(argument).collect::<Vec<_>>().join(", ")
+}
+
+fn argument(arg: &Argument) -> String {
+    let prefix = |each: bool, is_final: bool| {
+        format!(
+            "{}{}",
+            if each { "each " } else { "" },
+            if is_final { "final " } else { "" }
+        )
+    };
+    match arg {
+        Argument::Modification(arg) => {
+            let mut text = prefix(arg.each, arg.is_final) + &name(&arg.name);
+            if let Some(modification) = &arg.modification {
+                text.push_str(&self::modification(modification));
+            }
+            text + &description(&arg.description)
+        }
+        Argument::Redeclaration(arg) => format!(
+            "redeclare {}{}",
+            prefix(arg.each, arg.is_final),
+            argument_element(&arg.element)
+        ),
+        Argument::Replaceable(arg) => {
+            prefix(arg.each, arg.is_final) + &argument_element(&arg.element)
+        }
+        Argument::Empty => String::new(),
+    }
+}
+
+/// A short class definition or component clause1, prefixed with
+/// `replaceable` and followed by its constraining clause if replaceable.
+fn argument_element(element: &ArgumentElement) -> String {
+    let (replaceable, text, clause) = match element {
+        ArgumentElement::Class(def) => (
+            def.flags.replaceable,
+            class_prefixes(def) + &short_class_specifier(def),
+            &def.constraining_clause,
+        ),
+        ArgumentElement::Component(comp) => (
+            comp.flags.replaceable,
+            component_clause1(comp),
+            &comp.constraining_clause,
+        ),
+        ArgumentElement::Empty => return String::new(),
+    };
+    if replaceable {
+        format!("replaceable {}{}", text, constraining_clause(clause))
+    } else {
+        text
+    }
+}
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+// Expressions
+
+/// The precedence level of an expression.
+fn level(expr: &Expression) -> u8 {
+    match expr {
+        Expression::Binary(binary) => match binary.op {
+            BinaryOp::Or => OR,
+            BinaryOp::And => AND,
+            BinaryOp::LessThan
+            | BinaryOp::LessThanOrEqual
+            | BinaryOp::GreaterThan
+            | BinaryOp::GreaterThanOrEqual
+            | BinaryOp::Equal
+            | BinaryOp::NotEqual => RELATION,
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::ElemAdd | BinaryOp::ElemSub => ADD,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::ElemMul | BinaryOp::ElemDiv => MUL,
+            BinaryOp::Exp | BinaryOp::ElemExp => EXP,
+            BinaryOp::Not | BinaryOp::Paren | BinaryOp::Empty => PRIMARY,
+        },
+        Expression::Unary(unary) => match unary.op {
+            UnaryOp::Not => NOT,
+            UnaryOp::Negative
+            | UnaryOp::Positive
+            | UnaryOp::ElemNegative
+            | UnaryOp::ElemPositive => ADD,
+            UnaryOp::Paren | UnaryOp::Empty => PRIMARY,
+        },
+        Expression::Range(_) => RANGE,
+        Expression::If(_) => EXPRESSION,
+        _ => PRIMARY,
+    }
+}
+
+/// The highest level allowed for the right operand at the given level.
+fn below(level: u8) -> u8 {
+    match level {
+        OR => AND,
+        AND => NOT,
+        NOT => RELATION,
+        RELATION => ADD,
+        ADD => MUL,
+        MUL => EXP,
+        _ => PRIMARY,
+    }
+}
+
+/// Prints an expression, parenthesized if its level exceeds `max_level`.
+fn expression(expr: &Expression, max_level: u8) -> String {
+    let text = match expr {
+        Expression::Binary(binary) => {
+            let level = level(expr);
+            let lhs_level = if level == EXP { PRIMARY } else { level };
+            format!(
+                "{} {} {}",
+                expression(&binary.lhs, lhs_level),
+                binary_op(&binary.op),
+                expression(&binary.rhs, below(level))
+            )
+        }
+        Expression::Unary(unary) => match unary.op {
+            UnaryOp::Paren => format!("({})", expression(&unary.rhs, RANGE)),
+            UnaryOp::Not => format!("not {}", expression(&unary.rhs, NOT)),
+            UnaryOp::Negative => format!("-{}", expression(&unary.rhs, MUL)),
+            UnaryOp::Positive => format!("+{}", expression(&unary.rhs, MUL)),
+            UnaryOp::ElemNegative => format!(".-{}", expression(&unary.rhs, MUL)),
+            UnaryOp::ElemPositive => format!(".+{}", expression(&unary.rhs, MUL)),
+            UnaryOp::Empty => expression(&unary.rhs, max_level),
+        },
+        Expression::Range(range) => {
+            let mut text = expression(&range.start, OR);
+            if let Some(step) = &range.step {
+                text.push(':');
+                text.push_str(&expression(step, OR));
+            }
+            text.push(':');
+            text.push_str(&expression(&range.end, OR));
+            text
+        }
+        Expression::If(expr) => {
+            let mut text = String::new();
+            for (i, block) in expr.if_blocks.iter().enumerate() {
+                text.push_str(&format!(
+                    "{}{} then {}",
+                    if i == 0 { "if " } else { " elseif " },
+                    expression(&block.cond, RANGE),
+                    expression(&block.expr, RANGE)
+                ));
+            }
+            if let Some(else_expr) = &*expr.else_expr {
+                text.push_str(&format!(" else {}", expression(else_expr, RANGE)));
+            }
+            text
+        }
+        Expression::Array(array) => format!("{{{}}}", expressions(&array.args)),
+        Expression::ArrayComprehension(comp) => format!(
+            "{{{} for {}}}",
+            print_expression(&comp.expr),
+            for_indices(&comp.indices)
+        ),
+        Expression::Matrix(matrix) => {
+            let rows: Vec<_> = matrix
+                .rows
+                .iter()
+                .map(|row| expressions(&row.args))
+                .collect();
+            format!("[{}]", rows.join("; "))
+        }
+        Expression::Tuple(tuple) => format!("({})", output_expressions(&tuple.args)),
+        Expression::FunctionCall(call) => function_call(call),
+        Expression::FunctionPartialApplication(func) => format!(
+            "function {}({})",
+            type_specifier(&func.type_specifier),
+            named_arguments(&func.named_args)
+        ),
+        Expression::Ref(comp) => component_reference(comp),
+        Expression::UnsignedInteger(val) => val.val.clone(),
+        Expression::UnsignedReal(val) => val.val.clone(),
+        Expression::String(val) => format!("\"{}\"", val.raw),
+        Expression::Boolean(val) => val.val.to_string(),
+        Expression::End(_) => String::from("end"),
+        Expression::Empty => String::new(),
+    };
+    if level(expr) > max_level {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn binary_op(op: &BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "+",
+        BinaryOp::And => "and",
+        BinaryOp::Div => "/",
+        BinaryOp::ElemAdd => ".+",
+        BinaryOp::ElemDiv => "./",
+        BinaryOp::ElemExp => ".^",
+        BinaryOp::ElemMul => ".*",
+        BinaryOp::ElemSub => ".-",
+        BinaryOp::Equal => "==",
+        BinaryOp::Exp => "^",
+        BinaryOp::GreaterThan => ">",
+        BinaryOp::GreaterThanOrEqual => ">=",
+        BinaryOp::LessThan => "<",
+        BinaryOp::LessThanOrEqual => "<=",
+        BinaryOp::Mul => "*",
+        BinaryOp::NotEqual => "<>",
+        BinaryOp::Or => "or",
+        BinaryOp::Sub => "-",
+        BinaryOp::Not | BinaryOp::Paren | BinaryOp::Empty => "",
+    }
+}
+
+fn expressions(exprs: &[Expression]) -> String {
+    exprs
+        .iter()
+        .map(print_expression)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// An output expression list, where skipped outputs are left empty.
+fn output_expressions(exprs: &[Option<Expression>]) -> String {
+    exprs
+        .iter()
+        .map(|expr| expr.as_ref().map(print_expression).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn function_call(call: &FunctionCall) -> String {
+    let args = if call.indices.is_empty() {
+        let mut args: Vec<_> = call.args.iter().map(print_expression).collect();
+        if !call.named_args.is_empty() {
+            args.push(named_arguments(&call.named_args));
+        }
+        args.join(", ")
+    } else {
+        format!(
+            "{} for {}",
+            expressions(&call.args),
+            for_indices(&call.indices)
+        )
+    };
+    format!("{}({})", component_reference(&call.comp), args)
+}
+
+fn named_arguments(args: &[NamedArgument]) -> String {
+    args.iter()
+        .map(|arg| format!("{} = {}", arg.ident, print_expression(&arg.value)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn for_indices(indices: &[ForIndex]) -> String {
+    indices
+        .iter()
+        .map(|index| match &index.in_expr {
+            Some(expr) => format!("{} in {}", index.ident, print_expression(expr)),
+            None => index.ident.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn component_reference(comp: &ComponentReference) -> String {
+    let parts: Vec<_> = comp
+        .parts
+        .iter()
+        .map(|part| format!("{}{}", part.name, array_subscripts(&part.array_subscripts)))
+        .collect();
+    if comp.local {
+        format!(".{}", parts.join("."))
+    } else {
+        parts.join(".")
+    }
+}
+
+fn array_subscripts(subs: &[Subscript]) -> String {
+    if subs.is_empty() {
+        return String::new();
+    }
+    let subs: Vec<_> = subs
+        .iter()
+        .map(|sub| match sub {
+            Subscript::Range(_) => String::from(":"),
+            Subscript::Expression(expr) => print_expression(expr),
+            Subscript::Empty => String::new(),
+        })
+        .collect();
+    format!("[{}]", subs.join(", "))
+}
+
+// ━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━
+// Common
+
+fn equation_data(eq: &Equation) -> Option<&NodeData> {
+    match eq {
+        Equation::Simple(eq) => Some(&eq.node_data),
+        Equation::If(eq) => Some(&eq.node_data),
+        Equation::For(eq) => Some(&eq.node_data),
+        Equation::Connect(eq) => Some(&eq.node_data),
+        Equation::When(eq) => Some(&eq.node_data),
+        Equation::FunctionCall(eq) => Some(&eq.node_data),
+        Equation::Error(eq) => Some(&eq.node_data),
+        Equation::Empty => None,
+    }
+}
+
+fn statement_data(stmt: &Statement) -> Option<&NodeData> {
+    match stmt {
+        Statement::Assignment(stmt) => Some(&stmt.node_data),
+        Statement::FunctionCall(stmt) => Some(&stmt.node_data),
+        Statement::FunctionCallOutputs(stmt) => Some(&stmt.node_data),
+        Statement::If(stmt) => Some(&stmt.node_data),
+        Statement::For(stmt) => Some(&stmt.node_data),
+        Statement::While(stmt) => Some(&stmt.node_data),
+        Statement::When(stmt) => Some(&stmt.node_data),
+        Statement::Break(stmt) => Some(&stmt.node_data),
+        Statement::Return(stmt) => Some(&stmt.node_data),
+        Statement::Error(stmt) => Some(&stmt.node_data),
+        Statement::Empty => None,
+    }
+}
+
+fn argument_data(arg: &Argument) -> Option<&NodeData> {
+    match arg {
+        Argument::Modification(arg) => Some(&arg.node_data),
+        Argument::Redeclaration(arg) => Some(&arg.node_data),
+        Argument::Replaceable(arg) => Some(&arg.node_data),
+        Argument::Empty => None,
+    }
+}
+
+/// A description, preceded by a space unless it is absent.
+fn description(desc: &Option<Description>) -> String {
+    match desc {
+        // a description without strings was written for its annotation,
+        // even if that annotation is empty
+        Some(desc) if desc.strings.is_empty() => {
+            format!(" annotation({})", arguments(&desc.annotation))
+        }
+        Some(desc) => description_string(&desc.strings) + &annotation_clause(&desc.annotation),
+        None => String::new(),
+    }
+}
+
+fn description_string(strings: &[String]) -> String {
+    if strings.is_empty() {
+        return String::new();
+    }
+    let strings: Vec<_> = strings.iter().map(|s| string(s)).collect();
+    format!(" {}", strings.join(" + "))
+}
+
+fn annotation_clause(args: &[Argument]) -> String {
+    if args.is_empty() {
+        String::new()
+    } else {
+        format!(" annotation({})", arguments(args))
+    }
+}
+
+/// Quotes a string value, escaping it again.
+fn string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,11 @@
+//! This module contains the AST visitors.
+//!
+//! The `Visitor` and `VisitorMut` traits are generated by the build script
+//! from the definitions in `node`, so that they cover every node. Each node
+//! has `enter_*` and `exit_*` hooks, called before and after its children,
+//! and a `visit_*` method calling both around the matching `walk_*` function.
+//! Overriding `visit_*` without calling `walk_*` skips the children.
+
+use super::node::*;
+
+include!(concat!(env!("OUT_DIR"), "/visitor.rs"));
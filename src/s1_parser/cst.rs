@@ -0,0 +1,136 @@
+//! This module contains a lossless view of a parsed source.
+//!
+//! The AST drops whitespace and comments, but its nodes keep their byte
+//! span in `NodeData`. A `Cst` holds the source with its lossless token
+//! stream, so that the exact text of any node and the comments around it
+//! can be recovered from that span.
+//!
+//! Comments are attached by line: a node gets the comment lines above its
+//! first line and the trailing comment of its last line. Nodes sharing a
+//! line share these comments.
+
+use crate::s0_lexer::trivia::{tokenize, SyntaxKind, SyntaxToken};
+use crate::s1_parser::ast::part::NodeData;
+
+pub struct Cst<'input> {
+    pub source: &'input str,
+    /// Tokens and trivia, covering the whole source in order.
+    pub tokens: Vec<SyntaxToken>,
+}
+
+impl<'input> Cst<'input> {
+    pub fn new(source: &'input str) -> Self {
+        Cst {
+            source,
+            tokens: tokenize(source),
+        }
+    }
+
+    /// The source text of a node, as written.
+    pub fn text(&self, node_data: &NodeData) -> &'input str {
+        &self.source[node_data.span.0..node_data.span.1]
+    }
+
+    /// The tokens and trivia of a node.
+    pub fn tokens(&self, node_data: &NodeData) -> &[SyntaxToken] {
+        let (start, end) = node_data.span;
+        let first = self.tokens.partition_point(|token| token.span.0 < start);
+        let last = self.tokens.partition_point(|token| token.span.1 <= end);
+        &self.tokens[first..last.max(first)]
+    }
+
+    /// The index of the first token on the line where a node starts,
+    /// and the index past the last token on the line where it ends,
+    /// ignoring trivia.
+    fn line_bounds(&self, node_data: &NodeData) -> (usize, usize) {
+        let mut first = self
+            .tokens
+            .partition_point(|token| token.span.0 < node_data.span.0);
+        while first > 0 && self.tokens[first - 1].kind != SyntaxKind::Newline {
+            first -= 1;
+        }
+        while first < self.tokens.len() && self.tokens[first].kind.is_trivia() {
+            first += 1;
+        }
+        let mut last = self
+            .tokens
+            .partition_point(|token| token.span.1 <= node_data.span.1);
+        let mut i = last;
+        while i < self.tokens.len() && self.tokens[i].kind != SyntaxKind::Newline {
+            i += 1;
+            if !self.tokens[i - 1].kind.is_trivia() {
+                last = i;
+            }
+        }
+        (first, last)
+    }
+
+    /// The trivia on the lines above a node, and at the start of its line.
+    /// Trivia on the line of the previous token belongs to that token.
+    pub fn leading_trivia(&self, node_data: &NodeData) -> &[SyntaxToken] {
+        let (end, _) = self.line_bounds(node_data);
+        let mut start = end;
+        while start > 0 && self.tokens[start - 1].kind.is_trivia() {
+            start -= 1;
+        }
+        if start > 0 {
+            // skip the trailing trivia of the previous token
+            if let Some(newline) = self.tokens[start..end]
+                .iter()
+                .position(|token| token.kind == SyntaxKind::Newline)
+            {
+                start += newline + 1;
+            } else {
+                start = end;
+            }
+        }
+        &self.tokens[start..end]
+    }
+
+    /// The trivia at the end of the line where a node ends, such as a
+    /// trailing comment after its `;`.
+    pub fn trailing_trivia(&self, node_data: &NodeData) -> &[SyntaxToken] {
+        let (_, start) = self.line_bounds(node_data);
+        let mut end = start;
+        while end < self.tokens.len() && self.tokens[end].kind.is_trivia() {
+            end += 1;
+            if self.tokens[end - 1].kind == SyntaxKind::Newline {
+                break;
+            }
+        }
+        &self.tokens[start..end]
+    }
+
+    /// The comments attached to a node.
+    pub fn comments(&self, node_data: &NodeData) -> Vec<&'input str> {
+        self.leading_trivia(node_data)
+            .iter()
+            .chain(self.trailing_trivia(node_data))
+            .filter(|token| token.kind.is_comment())
+            .map(|token| token.text(self.source))
+            .collect()
+    }
+
+    /// The source text of the lines of a node, with its leading and
+    /// trailing trivia.
+    pub fn full_text(&self, node_data: &NodeData) -> &'input str {
+        let (start, end) = self.full_span(node_data);
+        &self.source[start..end]
+    }
+
+    /// The byte span of `full_text`.
+    pub fn full_span(&self, node_data: &NodeData) -> (usize, usize) {
+        let (first, last) = self.line_bounds(node_data);
+        let leading = self.leading_trivia(node_data);
+        let trailing = self.trailing_trivia(node_data);
+        let start = leading
+            .first()
+            .or(self.tokens.get(first))
+            .map_or(node_data.span.0, |token| token.span.0);
+        let end = trailing
+            .last()
+            .or(self.tokens.get(last.wrapping_sub(1)))
+            .map_or(node_data.span.1, |token| token.span.1);
+        (start, end)
+    }
+}
@@ -0,0 +1,107 @@
+//! This module contains the source formatter.
+//!
+//! The source is parsed and printed back with the layout options, keeping
+//! its comments and blank lines through the `Cst`. Since the printer works
+//! from the AST, formatting a formatted file leaves it unchanged.
+
+pub use super::ast::printer::FormatOptions;
+
+use super::ast::printer::print_with;
+use super::cst::Cst;
+use super::parser_helper::{parse, ParseDiagnostics};
+
+/// Lines of context around each change in a diff.
+const CONTEXT: usize = 3;
+
+/// Formats Modelica source, failing if it does not parse.
+pub fn format(
+    filename: &str,
+    source: &str,
+    options: &FormatOptions,
+) -> Result<String, ParseDiagnostics> {
+    let def = parse(filename, source)?;
+    let cst = Cst::new(source);
+    Ok(print_with(&def, options, Some(&cst)))
+}
+
+/// A unified diff from the original to the formatted source, empty if
+/// they are equal.
+pub fn diff(filename: &str, original: &str, formatted: &str) -> String {
+    let old: Vec<_> = original.split_inclusive('\n').collect();
+    let new: Vec<_> = formatted.split_inclusive('\n').collect();
+
+    // lcs[i][j] is the length of the longest common subsequence
+    // of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // the edit script, as (tag, old line, new line)
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push((' ', i, j));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            edits.push(('-', i, j));
+            i += 1;
+        } else {
+            edits.push(('+', i, j));
+            j += 1;
+        }
+    }
+
+    let changes: Vec<_> = (0..edits.len()).filter(|&k| edits[k].0 != ' ').collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+    let mut out = format!("--- {}\n+++ {}\n", filename, filename);
+    let mut k = 0;
+    while k < changes.len() {
+        // merge the changes whose context overlaps into a hunk
+        let start = changes[k].saturating_sub(CONTEXT);
+        while k + 1 < changes.len() && changes[k + 1] - changes[k] <= 2 * CONTEXT + 1 {
+            k += 1;
+        }
+        let end = (changes[k] + CONTEXT + 1).min(edits.len());
+        k += 1;
+
+        let hunk = &edits[start..end];
+        let old_count = hunk.iter().filter(|edit| edit.0 != '+').count();
+        let new_count = hunk.iter().filter(|edit| edit.0 != '-').count();
+        let (_, old_start, new_start) = hunk[0];
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        for &(tag, i, j) in hunk {
+            let line = if tag == '+' { new[j] } else { old[i] };
+            out.push(tag);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// The 1-based line range of a hunk, which starts after the preceding
+/// line when empty.
+fn hunk_range(start: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, count)
+    }
+}
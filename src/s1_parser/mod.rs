@@ -1,6 +1,12 @@
 pub mod ast;
+pub mod cst;
+pub mod formatter;
 pub mod parser_helper;
-pub use parser_helper::{parse, parse_file};
+pub mod source_map;
+pub use parser_helper::{
+    parse, parse_file, parse_with_recovery, parse_with_source_map, ParseDiagnostics,
+    ParseFileError, SyntaxError,
+};
 
 use lalrpop_util::lalrpop_mod;
 
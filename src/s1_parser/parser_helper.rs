@@ -1,81 +1,1007 @@
 //! This module contains parser helper functions.
 //!
 
-use crate::s0_lexer::lexer::Lexer;
-use crate::s0_lexer::tokens::LexicalError;
+use crate::s0_lexer::lexer::{Lexer, SpannedLexicalError};
+use crate::s0_lexer::tokens::{LexicalError, Token};
 
 use crate::s1_parser::ast::node;
 use crate::s1_parser::modelica::StoredDefinitionParser;
 
 use super::ast::part::ParserContext;
+use super::source_map::SourceMap;
 use codespan_reporting::diagnostic::{Diagnostic, Label};
 use codespan_reporting::files::SimpleFiles;
-use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+use codespan_reporting::term::termcolor::WriteColor;
 use lalrpop_util::ParseError;
 use md5;
-use std::process;
+use std::fmt;
 
-pub fn parse_file(filename: &str) -> node::StoredDefinition {
-    let file_txt = std::fs::read_to_string(filename).expect("failed to read file");
-    parse(filename, &file_txt)
+/// The error type produced by the generated LALRPOP parser.
+pub type ParserError = ParseError<usize, Token, SpannedLexicalError>;
+
+/// A single lexer or parser error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    /// Byte span of the offending input.
+    pub span: (usize, usize),
+    /// Terminals the parser would have accepted, empty for lexical errors.
+    pub expected: Vec<String>,
+    pub error: ParserError,
+}
+
+impl SyntaxError {
+    pub fn new(error: ParserError) -> Self {
+        let (span, expected) = match &error {
+            ParseError::User { error } => (error.span, Vec::new()),
+            ParseError::InvalidToken { location } => ((*location, *location), Vec::new()),
+            ParseError::UnrecognizedEof { location, expected } => {
+                ((*location, *location), expected.clone())
+            }
+            ParseError::UnrecognizedToken { token, expected } => {
+                ((token.0, token.2), expected.clone())
+            }
+            ParseError::ExtraToken { token } => ((token.0, token.2), Vec::new()),
+        };
+        SyntaxError {
+            span,
+            expected,
+            error,
+        }
+    }
+
+    /// The diagnostic code, stable across releases.
+    pub fn code(&self) -> &'static str {
+        match &self.error {
+            ParseError::UnrecognizedToken { .. } => "E001",
+            ParseError::UnrecognizedEof { .. } => "E002",
+            ParseError::ExtraToken { .. } => "E003",
+            ParseError::InvalidToken { .. } => "E004",
+            ParseError::User { .. } => "E005",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match &self.error {
+            ParseError::User { error } => match &error.error {
+                LexicalError::InvalidInteger(e) => format!("invalid integer: {}", e),
+                LexicalError::InvalidToken => "invalid token".to_string(),
+            },
+            ParseError::InvalidToken { .. } => "invalid token".to_string(),
+            ParseError::UnrecognizedEof { .. } => "unexpected end of file".to_string(),
+            ParseError::UnrecognizedToken { token, .. } => {
+                format!("unrecognized token {}", token.1)
+            }
+            ParseError::ExtraToken { token } => format!("extra token {}", token.1),
+        }
+    }
+
+    /// Converts the error to a codespan diagnostic labelled with `file_id`.
+    pub fn to_diagnostic(&self, file_id: usize) -> Diagnostic<usize> {
+        let mut diagnostic = Diagnostic::error()
+            .with_message(self.message())
+            .with_code(self.code())
+            .with_labels(vec![Label::primary(file_id, self.span.0..self.span.1)]);
+        if !self.expected.is_empty() {
+            diagnostic = diagnostic.with_notes(vec![format!(
+                "expected one of: {}",
+                self.expected.join(", ")
+            )]);
+        }
+        diagnostic
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "error[{}]: {} at {}..{}",
+            self.code(),
+            self.message(),
+            self.span.0,
+            self.span.1
+        )
+    }
+}
+
+/// The errors reported while parsing a single file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseDiagnostics {
+    pub file_id: usize,
+    pub file_name: String,
+    pub source: String,
+    pub errors: Vec<SyntaxError>,
+}
+
+impl ParseDiagnostics {
+    /// Converts all errors to codespan diagnostics labelled with `file_id`.
+    pub fn to_diagnostics(&self) -> Vec<Diagnostic<usize>> {
+        self.errors
+            .iter()
+            .map(|err| err.to_diagnostic(self.file_id))
+            .collect()
+    }
+
+    /// Renders all errors with codespan-reporting.
+    pub fn emit(
+        &self,
+        writer: &mut dyn WriteColor,
+    ) -> Result<(), codespan_reporting::files::Error> {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add(self.file_name.as_str(), self.source.as_str());
+        let config = codespan_reporting::term::Config::default();
+        for err in &self.errors {
+            codespan_reporting::term::emit(writer, &config, &files, &err.to_diagnostic(file_id))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ParseDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for err in &self.errors {
+            writeln!(f, "{}: {}", self.file_name, err)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseDiagnostics {}
+
+/// The error of `parse_file`: the file could not be read, or did not parse.
+#[derive(Debug)]
+pub enum ParseFileError {
+    Io {
+        file_name: String,
+        error: std::io::Error,
+    },
+    Syntax(ParseDiagnostics),
+}
+
+impl ParseFileError {
+    /// Renders the error with codespan-reporting.
+    pub fn emit(
+        &self,
+        writer: &mut dyn WriteColor,
+    ) -> Result<(), codespan_reporting::files::Error> {
+        match self {
+            ParseFileError::Io { .. } => {
+                let files = SimpleFiles::<&str, &str>::new();
+                let config = codespan_reporting::term::Config::default();
+                let diagnostic = Diagnostic::error().with_message(self.to_string());
+                codespan_reporting::term::emit(writer, &config, &files, &diagnostic)
+            }
+            ParseFileError::Syntax(diagnostics) => diagnostics.emit(writer),
+        }
+    }
+}
+
+impl fmt::Display for ParseFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseFileError::Io { file_name, error } => {
+                write!(f, "failed to read {}: {}", file_name, error)
+            }
+            ParseFileError::Syntax(diagnostics) => write!(f, "{}", diagnostics),
+        }
+    }
+}
+
+impl std::error::Error for ParseFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseFileError::Io { error, .. } => Some(error),
+            ParseFileError::Syntax(diagnostics) => Some(diagnostics),
+        }
+    }
+}
+
+impl From<ParseDiagnostics> for ParseFileError {
+    fn from(diagnostics: ParseDiagnostics) -> Self {
+        ParseFileError::Syntax(diagnostics)
+    }
+}
+
+/// Reads and parses a file, failing if it cannot be read or any syntax
+/// error is found.
+pub fn parse_file(filename: &str) -> Result<node::StoredDefinition, ParseFileError> {
+    let file_txt = std::fs::read_to_string(filename).map_err(|error| ParseFileError::Io {
+        file_name: filename.to_string(),
+        error,
+    })?;
+    Ok(parse(filename, &file_txt)?)
+}
+
+/// Parses a file, failing if any syntax error is found.
+pub fn parse(filename: &str, file_txt: &str) -> Result<node::StoredDefinition, ParseDiagnostics> {
+    let (def, diagnostics) = parse_with_recovery(filename, file_txt);
+    match def {
+        Some(def) if diagnostics.errors.is_empty() => Ok(def),
+        _ => Err(diagnostics),
+    }
+}
+
+/// Parses a file, recovering from syntax errors at equation, statement,
+/// element and class boundaries. The input of lexical errors is skipped.
+///
+/// Returns the partially populated definition, unless the parser could
+/// not recover, along with every error encountered.
+pub fn parse_with_recovery(
+    filename: &str,
+    file_txt: &str,
+) -> (Option<node::StoredDefinition>, ParseDiagnostics) {
+    parse_file_id(0, filename, file_txt)
+}
+
+/// Adds a file to the source map and parses it, failing if any syntax
+/// error is found. The nodes and diagnostics carry the file id of the file,
+/// so that several files can be parsed into the same source map.
+pub fn parse_with_source_map(
+    source_map: &mut SourceMap,
+    filename: &str,
+    file_txt: &str,
+) -> Result<node::StoredDefinition, ParseDiagnostics> {
+    let file_id = source_map.add_file(filename, file_txt);
+    match parse_file_id(file_id, filename, file_txt) {
+        (Some(def), diagnostics) if diagnostics.errors.is_empty() => Ok(def),
+        (_, diagnostics) => Err(diagnostics),
+    }
 }
 
-pub fn parse(filename: &str, file_txt: &str) -> node::StoredDefinition {
-    let mut files = SimpleFiles::new();
-    let file_id = files.add(filename, file_txt);
-    let file = files.get(file_id).expect("failed to get file id");
-    let file_txt = file.source();
-    let lexer = Lexer::new(file_txt);
+fn parse_file_id(
+    file_id: usize,
+    filename: &str,
+    file_txt: &str,
+) -> (Option<node::StoredDefinition>, ParseDiagnostics) {
+    // lexical errors are recorded and the offending input skipped, so that
+    // they do not abort the parse
+    let mut lexical_errors = Vec::new();
+    let tokens = Lexer::new(file_txt).filter_map(|token| match token {
+        Ok(token) => Some(Ok(token)),
+        Err(error) => {
+            lexical_errors.push(SyntaxError::new(ParseError::User { error }));
+            None
+        }
+    });
     let parser = StoredDefinitionParser::new();
     let mut context = ParserContext::default();
-    let result = parser.parse(&mut context, lexer);
-    if result.is_err() {
-        let err = result.as_ref().expect_err("error");
-        let writer = StandardStream::stderr(ColorChoice::Always);
-        let config = codespan_reporting::term::Config::default();
+    context.file_id = file_id;
+    let result = parser.parse(&mut context, tokens);
+
+    let mut errors: Vec<SyntaxError> = std::mem::take(&mut context.errors)
+        .into_iter()
+        .map(|recovery| SyntaxError::new(recovery.error))
+        .chain(lexical_errors)
+        .collect();
+    let def = match result {
+        Ok(mut def) => {
+            let digest = md5::compute(file_txt);
+            def.model_md5 = format!("{:x}", digest);
+            def.rumoca_parser_version = env!("CARGO_PKG_VERSION").to_string();
+            def.rumoca_parser_git = option_env!("GIT_VER").unwrap_or("").to_string();
+            Some(def)
+        }
+        Err(err) => {
+            errors.push(SyntaxError::new(err));
+            None
+        }
+    };
+    errors.sort_by_key(|err| err.span);
+    let diagnostics = ParseDiagnostics {
+        file_id,
+        file_name: filename.to_string(),
+        source: file_txt.to_string(),
+        errors,
+    };
+    (def, diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::s1_parser::ast::part;
+
+    #[test]
+    fn test_parse_models() {
+        for entry in std::fs::read_dir("tests/models").unwrap() {
+            let path = entry.unwrap().path();
+            let filename = path.to_str().unwrap();
+            if let Err(err) = parse_file(filename) {
+                panic!("{}", err);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let err = parse("test.mo", "model A Real x end A;").unwrap_err();
+        assert_eq!(err.errors.len(), 1);
+        assert_eq!(err.errors[0].span, (15, 18));
+        assert_eq!(err.errors[0].code(), "E001");
+        assert!(err.errors[0].expected.contains(&"\";\"".to_string()));
+    }
+
+    #[test]
+    fn test_parse_recovery() {
+        let source = "model A
+    Real x;
+    Real y z;
+    Real w;
+equation
+    x = ;
+    y = 2;
+algorithm
+    w := := 1;
+end A;
+";
+        let (def, diagnostics) = parse_with_recovery("test.mo", source);
+        let def = def.unwrap();
+        assert_eq!(diagnostics.errors.len(), 3);
+        let class = &def.classes["A"];
+        assert!(class.components.contains_key("x"));
+        assert!(class.components.contains_key("w"));
+        assert_eq!(class.equations.len(), 2);
+        assert!(matches!(class.equations[0], node::Equation::Error(_)));
+        assert!(matches!(class.equations[1], node::Equation::Simple(_)));
+        assert!(matches!(class.algorithms[0][0], node::Statement::Error(_)));
+
+        // a missing ";" resyncs on the "end" closing the list
+        let source = "model A
+    Real x;
+    Real y
+end A;
+model B
+equation
+    x = 1
+end B;
+model C(x) end C;
+model D end D;
+";
+        let (def, diagnostics) = parse_with_recovery("test.mo", source);
+        let def = def.unwrap();
+        assert_eq!(diagnostics.errors.len(), 3);
+        assert!(diagnostics.errors.iter().all(|err| err.code() == "E001"));
+        let a = &def.classes["A"];
+        assert!(a.components.contains_key("x"));
+        assert!(!a.components.contains_key("y"));
+        let b = &def.classes["B"];
+        assert_eq!(b.equations.len(), 1);
+        assert!(matches!(b.equations[0], node::Equation::Error(_)));
+        assert!(def.classes["C"].components.is_empty());
+        assert!(def.classes.contains_key("D"));
+    }
+
+    #[test]
+    fn test_parse_when() {
+        let def = parse_file("tests/models/when.mo").unwrap();
+        let class = &def.classes["When"];
+        let node::Equation::When(eq) = &class.equations[1] else {
+            panic!("expected when equation");
+        };
+        assert_eq!(eq.when_blocks.len(), 3);
+        assert!(matches!(eq.when_blocks[1].eqs[1], node::Equation::When(_)));
+        let node::Statement::When(stmt) = &class.algorithms[0][0] else {
+            panic!("expected when statement");
+        };
+        assert_eq!(stmt.when_blocks.len(), 2);
+        assert!(matches!(
+            stmt.when_blocks[1].stmts[1],
+            node::Statement::When(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_short_class() {
+        let def = parse_file("tests/models/types.mo").unwrap();
+        let package = &def.classes["Types"];
+        let voltage = &package.classes["Voltage"];
+        assert_eq!(voltage.base_type.as_ref().unwrap().name.parts, ["Real"]);
+        assert_eq!(voltage.modification.len(), 2);
+        assert_eq!(package.classes["Position"].array_subscripts.len(), 1);
+        let input = &package.classes["RealInput"];
+        assert_eq!(input.base_prefix, part::Causality::Input);
+        assert_eq!(input.annotation.len(), 1);
+        let color = package.classes["Color"].enumeration.as_ref().unwrap();
+        assert_eq!(color.literals.len(), 3);
+        assert!(color.literals[0].description.is_some());
+        let unspecified = package.classes["Unspecified"].enumeration.as_ref().unwrap();
+        assert!(unspecified.unspecified);
+    }
+
+    #[test]
+    fn test_parse_imports() {
+        let def = parse_file("tests/models/imports.mo").unwrap();
+        let package = &def.classes["Imports"];
+        let kinds: Vec<_> = package.imports.iter().map(|i| i.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            [
+                part::ImportKind::Qualified,
+                part::ImportKind::Renaming,
+                part::ImportKind::Unqualified,
+                part::ImportKind::Multiple,
+            ]
+        );
+        assert_eq!(package.imports[1].alias, "SI");
+        assert_eq!(package.imports[1].name.parts, ["Modelica", "Units", "SI"]);
+        assert_eq!(package.imports[3].import_list, ["sin", "cos"]);
+        let pendulum = &package.classes["Pendulum"];
+        assert_eq!(pendulum.imports.len(), 2);
+        assert_eq!(pendulum.imports[1].kind, part::ImportKind::Unqualified);
+    }
+
+    #[test]
+    fn test_parse_named_arguments() {
+        let def = parse_file("tests/models/functions.mo").unwrap();
+        let test = &def.classes["Functions"].classes["Test"];
+        let calls: Vec<_> = test
+            .equations
+            .iter()
+            .map(|eq| match eq {
+                node::Equation::Simple(node::EquationSimple {
+                    rhs: node::Expression::FunctionCall(call),
+                    ..
+                }) => call,
+                _ => panic!("expected function call"),
+            })
+            .collect();
+        assert_eq!(calls[0].args.len(), 1);
+        assert_eq!(calls[0].named_args.len(), 2);
+        assert_eq!(calls[0].named_args[1].ident, "b");
+        assert!(calls[1].args.is_empty());
+        assert_eq!(calls[1].named_args.len(), 2);
+        let node::Expression::FunctionPartialApplication(partial) = &calls[2].args[0] else {
+            panic!("expected partial application");
+        };
+        assert_eq!(partial.type_specifier.name.parts, ["quadratic"]);
+        assert_eq!(partial.named_args.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_comprehensions() {
+        let def = parse_file("tests/models/comprehensions.mo").unwrap();
+        let class = &def.classes["Comprehensions"];
+        let Some(node::Modification::Expression(node::ModExpr::Expression(
+            node::Expression::ArrayComprehension(array),
+        ))) = &class.components["A"].modification
+        else {
+            panic!("expected array comprehension");
+        };
+        assert_eq!(array.indices.len(), 2);
+        assert_eq!(array.indices[1].ident, "j");
+        let node::Equation::Simple(node::EquationSimple {
+            rhs: node::Expression::FunctionCall(call),
+            ..
+        }) = &class.equations[1]
+        else {
+            panic!("expected reduction");
+        };
+        assert_eq!(call.args.len(), 1);
+        assert_eq!(call.indices.len(), 1);
+        assert!(call.indices[0].in_expr.is_some());
+    }
+
+    #[test]
+    fn test_parse_matrix() {
+        let def = parse_file("tests/models/statespace.mo").unwrap();
+        let class = &def.classes["StateSpace"];
+        let matrix = |name: &str| match &class.components[name].modification {
+            Some(node::Modification::Expression(node::ModExpr::Expression(
+                node::Expression::Matrix(matrix),
+            ))) => matrix.clone(),
+            _ => panic!("expected matrix"),
+        };
+        let a = matrix("A");
+        assert_eq!(a.rows.len(), 2);
+        assert_eq!(a.rows[1].args.len(), 2);
+        assert_eq!(matrix("B").rows.len(), 2);
+        assert_eq!(matrix("C").rows[0].args.len(), 2);
+    }
 
-        match err {
-            ParseError::User { error } => match error {
-                LexicalError::InvalidInteger(e) => {
-                    println!("lexer invalid integer:{:?}", e);
+    #[test]
+    fn test_parse_strings() {
+        let def = parse_file("tests/models/strings.mo").unwrap();
+        let class = &def.classes["Strings"];
+        assert_eq!(
+            class.description.parts,
+            ["Strings: punctuation, \"escapes\" and Unicode (ü)"]
+        );
+        let Some(node::Modification::Expression(node::ModExpr::Expression(
+            node::Expression::String(name),
+        ))) = &class.components["name"].modification
+        else {
+            panic!("expected string");
+        };
+        assert_eq!(name.raw, "ball \\\"one\\\"\\n");
+        assert_eq!(name.val, "ball \"one\"\n");
+        let description = class.components["m"].description.as_ref().unwrap();
+        assert_eq!(description.strings, ["Mass [kg]"]);
+    }
+
+    #[test]
+    fn test_parse_quoted_identifiers() {
+        let def = parse_file("tests/models/quoted.mo").unwrap();
+        let class = &def.classes["'Quoted Model'"];
+        let length = &class.components["L"];
+        assert!(!length.quoted);
+        assert_eq!(length.type_specifier.name.quoted, [true, false]);
+        let xy = &class.components["'x.y'"];
+        assert!(xy.quoted);
+        assert!(class.components.contains_key(r"'it\'s'"));
+        let node::Equation::Simple(eq) = &class.equations[1] else {
+            panic!("expected simple equation");
+        };
+        let node::Expression::Ref(comp) = &eq.lhs else {
+            panic!("expected component reference");
+        };
+        assert_eq!(comp.parts[0].name, "'end'");
+        assert!(comp.parts[0].quoted);
+    }
+
+    #[test]
+    fn test_parse_external() {
+        let def = parse_file("tests/models/external.mo").unwrap();
+        let package = &def.classes["Drivers"];
+        let read = package.classes["readSensor"].external.as_ref().unwrap();
+        assert_eq!(read.language.as_deref(), Some("C"));
+        assert_eq!(read.output.as_ref().unwrap().parts[0].name, "value");
+        assert_eq!(read.function.as_deref(), Some("read_sensor"));
+        assert_eq!(read.args.len(), 1);
+        assert_eq!(read.annotation.len(), 2);
+        let lookup = package.classes["lookup"].external.as_ref().unwrap();
+        assert_eq!(lookup.language.as_deref(), Some("FORTRAN 77"));
+        assert!(lookup.output.is_none());
+        assert_eq!(lookup.args.len(), 2);
+        let builtin = package.classes["fastSin"].external.as_ref().unwrap();
+        assert_eq!(builtin.language.as_deref(), Some("builtin"));
+        assert!(builtin.function.is_none());
+        let default = package.classes["defaultCall"].external.as_ref().unwrap();
+        assert!(default.language.is_none());
+    }
+
+    #[test]
+    fn test_parse_replaceable() {
+        let def = parse_file("tests/models/replaceable.mo").unwrap();
+        let package = &def.classes["Fluids"];
+
+        let pipe = &package.classes["Pipe"];
+        let medium = &pipe.classes["Medium"];
+        assert!(medium.flags.replaceable);
+        let clause = medium.constraining_clause.as_ref().unwrap();
+        assert_eq!(clause.type_specifier.name.parts, vec!["PartialMedium"]);
+        assert_eq!(
+            clause.description.as_ref().unwrap().strings,
+            vec!["medium model"]
+        );
+        let p = &pipe.components["p"];
+        assert!(p.flags.replaceable);
+        let clause = p.constraining_clause.as_ref().unwrap();
+        assert_eq!(clause.modification.len(), 1);
+        assert!(pipe.components["length"].constraining_clause.is_none());
+
+        let system = &package.classes["System"];
+        let args = |name: &str| match &system.components[name].modification {
+            Some(node::Modification::Class(modification)) => modification.args.clone(),
+            _ => panic!("expected class modification"),
+        };
+        match &args("pipe1")[0] {
+            node::Argument::Redeclaration(redeclare) => {
+                assert!(!redeclare.each);
+                match &redeclare.element {
+                    node::ArgumentElement::Class(def) => {
+                        assert_eq!(def.name, "Medium");
+                        assert_eq!(def.class_type, part::ClassType::Package);
+                        assert!(!def.flags.replaceable);
+                    }
+                    _ => panic!("expected class redeclaration"),
                 }
-                LexicalError::InvalidToken => {
-                    println!("lexer invalid token {:?}", error);
+            }
+            _ => panic!("expected redeclaration"),
+        }
+        let pipe2 = args("pipe2");
+        match &pipe2[0] {
+            node::Argument::Redeclaration(redeclare) => {
+                assert!(redeclare.each && redeclare.is_final);
+            }
+            _ => panic!("expected redeclaration"),
+        }
+        match &pipe2[1] {
+            node::Argument::Redeclaration(node::ArgumentRedeclaration {
+                element: node::ArgumentElement::Component(comp),
+                ..
+            }) => {
+                assert_eq!(comp.name, "p");
+                assert!(comp.flags.replaceable);
+                assert!(comp.constraining_clause.is_some());
+            }
+            _ => panic!("expected replaceable component redeclaration"),
+        }
+        match &args("pipe3")[0] {
+            node::Argument::Replaceable(replaceable) => match &replaceable.element {
+                node::ArgumentElement::Class(def) => {
+                    assert!(def.flags.replaceable);
+                    assert!(def.constraining_clause.is_some());
                 }
+                _ => panic!("expected replaceable class"),
             },
-            ParseError::InvalidToken { location } => {
-                println!("invalid token loc:{:?}", location);
+            _ => panic!("expected replaceable"),
+        }
+    }
+
+    #[test]
+    fn test_parse_extends() {
+        let def = parse_file("tests/models/extends.mo").unwrap();
+        let package = &def.classes["Circuits"];
+
+        let resistor = &package.classes["Resistor"];
+        assert_eq!(resistor.extends.len(), 1);
+        let extends = &resistor.extends[0];
+        assert_eq!(extends.type_specifier.name.parts, vec!["TwoPin"]);
+        assert_eq!(extends.modification.len(), 1);
+        assert!(matches!(
+            extends.modification[0],
+            node::ExtendsArgument::Argument(node::Argument::Modification(_))
+        ));
+        assert_eq!(extends.annotation.len(), 1);
+
+        let shorted = &package.classes["ShortedResistor"];
+        let modification = &shorted.extends[0].modification;
+        assert_eq!(modification.len(), 2);
+        match &modification[0] {
+            node::ExtendsArgument::Inheritance(node::InheritanceModification::Connect(connect)) => {
+                assert_eq!(connect.lhs.parts[0].name, "p");
+                assert_eq!(connect.rhs.parts[0].name, "n");
             }
-            ParseError::ExtraToken { token } => {
-                println!("extra token: {:?}", token);
+            _ => panic!("expected break connect"),
+        }
+        match &modification[1] {
+            node::ExtendsArgument::Inheritance(node::InheritanceModification::Ident(ident)) => {
+                assert_eq!(ident.ident, "i");
             }
-            ParseError::UnrecognizedEof { location, expected } => {
-                println!("unrecognized Eof loc: {:?}, expected:", location);
-                for tok in expected {
-                    println!("{:?}", tok)
+            _ => panic!("expected break ident"),
+        }
+        assert_eq!(package.classes["Ground"].extends.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_function_outputs() {
+        let def = parse_file("tests/models/outputs.mo").unwrap();
+        let model = &def.classes["Estimator"];
+
+        let algorithm = &model.algorithms[0];
+        match &algorithm[0] {
+            node::Statement::FunctionCallOutputs(stmt) => {
+                assert_eq!(stmt.outputs.len(), 2);
+                assert!(stmt.outputs.iter().all(|output| output.is_some()));
+                assert_eq!(stmt.call.comp.parts[0].name, "predict");
+                assert_eq!(stmt.call.args.len(), 2);
+            }
+            _ => panic!("expected multiple output call"),
+        }
+        match &algorithm[1] {
+            node::Statement::FunctionCallOutputs(stmt) => {
+                assert_eq!(stmt.outputs.len(), 2);
+                assert!(stmt.outputs[0].is_none());
+            }
+            _ => panic!("expected multiple output call"),
+        }
+        match &algorithm[2] {
+            node::Statement::FunctionCallOutputs(stmt) => assert_eq!(stmt.outputs.len(), 1),
+            _ => panic!("expected single output call"),
+        }
+
+        match &model.equations[0] {
+            node::Equation::Simple(eq) => match &eq.lhs {
+                node::Expression::Tuple(tuple) => {
+                    assert_eq!(tuple.args.len(), 3);
+                    assert!(tuple.args[0].is_some());
+                    assert!(tuple.args[1].is_none());
+                    assert!(tuple.args[2].is_some());
                 }
+                _ => panic!("expected tuple"),
+            },
+            _ => panic!("expected simple equation"),
+        }
+        match &model.equations[1] {
+            node::Equation::Simple(eq) => assert!(matches!(eq.rhs, node::Expression::Binary(_))),
+            _ => panic!("expected simple equation"),
+        }
+    }
+
+    #[test]
+    fn test_parse_function_call_equations() {
+        let def = parse_file("tests/models/bouncingball.mo").unwrap();
+        let model = &def.classes["BouncingBall"];
+        match &model.equations[2] {
+            node::Equation::When(eq) => match &eq.when_blocks[0].eqs[0] {
+                node::Equation::FunctionCall(eq) => {
+                    assert_eq!(eq.call.comp.parts[0].name, "reinit");
+                    assert_eq!(eq.call.args.len(), 2);
+                }
+                _ => panic!("expected function call equation"),
+            },
+            _ => panic!("expected when equation"),
+        }
+
+        let def = parse_file("tests/models/asserts.mo").unwrap();
+        let model = &def.classes["Checked"];
+        match &model.equations[1] {
+            node::Equation::FunctionCall(eq) => {
+                assert_eq!(eq.call.comp.parts[0].name, "assert");
+                assert_eq!(eq.call.args.len(), 2);
+                assert!(eq.description.is_some());
             }
-            ParseError::UnrecognizedToken { token, expected } => {
-                let diagonistic = Diagnostic::error()
-                    .with_message("Unrecognized Token")
-                    .with_code("E001")
-                    .with_labels(vec![
-                        Label::primary(file_id, (token.0)..(token.2)),
-                        Label::secondary(file_id, 0..(token.2)),
-                    ])
-                    .with_notes(vec!["expected one of: ".to_string(), expected.join(", ")]);
-                codespan_reporting::term::emit(&mut writer.lock(), &config, &files, &diagonistic)
-                    .expect("fail");
-            }
-        }
-
-        // kill process to avoid panicing when parse fails, codespan already reports error
-        process::exit(1);
-    }
-    let mut def = result.unwrap();
-    let digest = md5::compute(file_txt);
-    def.model_md5 = format!("{:x}", digest);
-    def.rumoca_parser_version = env!("CARGO_PKG_VERSION").to_string();
-    def.rumoca_parser_git = option_env!("GIT_VER").unwrap_or("").to_string();
-    def
+            _ => panic!("expected function call equation"),
+        }
+        match &model.algorithms[0][0] {
+            node::Statement::FunctionCall(stmt) => {
+                assert_eq!(stmt.call.comp.parts.len(), 4);
+                assert_eq!(stmt.call.comp.parts[3].name, "print");
+            }
+            _ => panic!("expected function call statement"),
+        }
+        match &model.algorithms[0][1] {
+            node::Statement::If(stmt) => assert!(matches!(
+                stmt.if_blocks[0].stmts[0],
+                node::Statement::FunctionCall(_)
+            )),
+            _ => panic!("expected if statement"),
+        }
+    }
+
+    #[test]
+    fn test_parse_subscript_end() {
+        let def = parse_file("tests/models/ringbuffer.mo").unwrap();
+        let model = &def.classes["RingBuffer"];
+        let rhs = |i: usize| match &model.equations[i] {
+            node::Equation::Simple(eq) => eq.rhs.clone(),
+            _ => panic!("expected simple equation"),
+        };
+        let subscripts = |expr: node::Expression| match expr {
+            node::Expression::Ref(comp) => comp.parts[0].array_subscripts.clone(),
+            _ => panic!("expected component reference"),
+        };
+        match &subscripts(rhs(0))[0] {
+            node::Subscript::Expression(node::Expression::End(_)) => {}
+            _ => panic!("expected end"),
+        }
+        match &subscripts(rhs(1))[0] {
+            node::Subscript::Expression(node::Expression::Binary(binary)) => {
+                assert!(matches!(*binary.lhs, node::Expression::End(_)));
+            }
+            _ => panic!("expected end - 1"),
+        }
+        assert_eq!(subscripts(rhs(2)).len(), 1);
+        // a matrix row separator inside subscripts
+        assert_eq!(subscripts(rhs(4)).len(), 1);
+
+        let result = parse("test.mo", "model M Real x = {1, end}; end M;");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_derivative() {
+        let def = parse_file("tests/models/derivatives.mo").unwrap();
+        let package = &def.classes["Sensitivities"];
+
+        let fx = &package.classes["Fx"];
+        assert_eq!(fx.class_type, part::ClassType::Function);
+        let derivative = fx.derivative.as_ref().unwrap();
+        assert_eq!(derivative.type_specifier.name.parts, vec!["F"]);
+        assert_eq!(derivative.idents, vec!["x"]);
+        assert_eq!(fx.description.parts, vec!["partial derivative"]);
+
+        let df = &package.classes["DF"];
+        assert_eq!(df.class_type, part::ClassType::Type);
+        let derivative = df.derivative.as_ref().unwrap();
+        assert_eq!(derivative.idents, vec!["x", "y"]);
+        assert!(package.classes["F"].derivative.is_none());
+    }
+
+    #[test]
+    fn test_parse_class_annotation() {
+        let def = parse_file("tests/models/bouncingball.mo").unwrap();
+        let model = &def.classes["BouncingBall"];
+        assert_eq!(model.annotation.len(), 2);
+        let experiment = model.experiment().unwrap();
+        assert_eq!(experiment.start_time, Some(0.0));
+        assert_eq!(experiment.stop_time, Some(10.0));
+        assert_eq!(experiment.tolerance, Some(1e-6));
+        assert_eq!(experiment.interval, Some(0.01));
+
+        let def = parse_file("tests/models/integrator.mo").unwrap();
+        assert!(def.classes["Integrator"].experiment().is_none());
+
+        let def = parse(
+            "test.mo",
+            "model M annotation(experiment(StopTime = -1)); end M;",
+        )
+        .unwrap();
+        let experiment = def.classes["M"].experiment().unwrap();
+        assert_eq!(experiment.start_time, None);
+        assert_eq!(experiment.stop_time, Some(-1.0));
+    }
+
+    #[test]
+    fn test_visitor() {
+        use crate::s1_parser::ast::visitor::{Visitor, VisitorMut};
+
+        #[derive(Default)]
+        struct References {
+            names: Vec<String>,
+            depth: usize,
+            max_depth: usize,
+        }
+
+        impl Visitor for References {
+            fn enter_component_reference(&mut self, node: &node::ComponentReference) {
+                let parts: Vec<_> = node.parts.iter().map(|part| part.name.clone()).collect();
+                self.names.push(parts.join("."));
+            }
+            fn enter_expression(&mut self, _node: &node::Expression) {
+                self.depth += 1;
+                self.max_depth = self.max_depth.max(self.depth);
+            }
+            fn exit_expression(&mut self, _node: &node::Expression) {
+                self.depth -= 1;
+            }
+            // skip the class annotation
+            fn visit_argument(&mut self, _node: &node::Argument) {}
+        }
+
+        struct Rename;
+
+        impl VisitorMut for Rename {
+            fn enter_ref_part_mut(&mut self, node: &mut node::RefPart) {
+                if node.name == "velocity" {
+                    node.name = String::from("v");
+                }
+            }
+        }
+
+        let mut def = parse_file("tests/models/bouncingball.mo").unwrap();
+        let mut references = References::default();
+        references.visit_stored_definition(&def);
+        assert_eq!(
+            references.names,
+            vec![
+                "der", "height", "velocity", "der", "velocity", "g", "height", "radius", "reinit",
+                "velocity", "c", "pre", "velocity"
+            ]
+        );
+        assert_eq!(references.depth, 0);
+        assert_eq!(references.max_depth, 4);
+
+        Rename.visit_stored_definition_mut(&mut def);
+        let mut references = References::default();
+        references.visit_stored_definition(&def);
+        assert_eq!(
+            references.names.iter().filter(|name| *name == "v").count(),
+            4
+        );
+        assert!(!references.names.iter().any(|name| name == "velocity"));
+    }
+
+    #[test]
+    fn test_cst() {
+        use crate::s1_parser::cst::Cst;
+
+        let source = std::fs::read_to_string("tests/models/comments.mo").unwrap();
+        let def = parse("tests/models/comments.mo", &source).unwrap();
+        let cst = Cst::new(&source);
+        let tank = &def.classes["Tank"];
+        assert!(cst.text(&tank.node_data).starts_with("model Tank"));
+        assert_eq!(
+            cst.comments(&tank.node_data),
+            ["/* A tank,\n   filled through a valve */"]
+        );
+
+        let area = &tank.components["area"];
+        assert_eq!(cst.text(&area.node_data), "area = 2");
+        assert_eq!(cst.comments(&area.node_data), ["// geometry", "// [m2]"]);
+        let level = &tank.components["level"];
+        assert!(cst.comments(&level.node_data).is_empty());
+        assert!(cst
+            .tokens(&level.node_data)
+            .iter()
+            .any(|token| token.kind.is_comment()));
+        let q = &tank.components["q"];
+        assert_eq!(cst.comments(&q.node_data), ["/* inflow */"]);
+
+        let balance = match &tank.equations[0] {
+            node::Equation::Simple(eq) => eq,
+            _ => panic!("expected simple equation"),
+        };
+        assert_eq!(cst.text(&balance.node_data), "area * der(level) = q");
+        assert_eq!(
+            cst.comments(&balance.node_data),
+            ["// mass balance", "// no outflow"]
+        );
+        assert_eq!(
+            cst.full_text(&balance.node_data),
+            "    // mass balance\n    area * der(level) = q; // no outflow\n"
+        );
+        let tokens = cst.tokens(&balance.node_data);
+        assert_eq!(tokens.first().unwrap().span.0, balance.node_data.span.0);
+        assert_eq!(tokens.last().unwrap().span.1, balance.node_data.span.1);
+    }
+
+    #[test]
+    fn test_source_map() {
+        use crate::s1_parser::source_map::LineCol;
+
+        let mut source_map = SourceMap::default();
+        let first =
+            parse_with_source_map(&mut source_map, "a.mo", "model A\n    Real x;\nend A;\n")
+                .unwrap();
+        let second = parse_with_source_map(
+            &mut source_map,
+            "b.mo",
+            "model B\n    Real y = 1 \"π😀\"; Real z;\nend B;\n",
+        )
+        .unwrap();
+        let x = &first.classes["A"].components["x"];
+        let z = &second.classes["B"].components["z"];
+        assert_eq!(x.node_data.file_id, 0);
+        assert_eq!(z.node_data.file_id, 1);
+        assert_eq!(source_map.file(1).unwrap().name, "b.mo");
+        assert_eq!(source_map.text(&x.node_data), "x");
+        assert_eq!(source_map.text(&z.node_data), "z");
+
+        assert_eq!(
+            source_map.line_col(&x.node_data),
+            (LineCol { line: 1, col: 9 }, LineCol { line: 1, col: 10 })
+        );
+        // π is 2 bytes and 1 UTF-16 unit, 😀 is 4 bytes and 2 units
+        assert_eq!(
+            source_map.line_col(&z.node_data).0,
+            LineCol { line: 1, col: 30 }
+        );
+        assert_eq!(
+            source_map.line_col_utf16(&z.node_data).0,
+            LineCol { line: 1, col: 27 }
+        );
+
+        let index = &source_map.file(1).unwrap().line_index;
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_col(0), LineCol { line: 0, col: 0 });
+        assert_eq!(index.line_col(7), LineCol { line: 0, col: 7 });
+        assert_eq!(index.line_col(8), LineCol { line: 1, col: 0 });
+        assert_eq!(index.line_col(1000), LineCol { line: 3, col: 0 });
+
+        let err =
+            parse_with_source_map(&mut source_map, "c.mo", "model C Real x end C;").unwrap_err();
+        assert_eq!(err.file_id, 2);
+    }
+
+    #[test]
+    fn test_parse_file_missing() {
+        let err = parse_file("tests/models/missing.mo").unwrap_err();
+        match &err {
+            ParseFileError::Io { file_name, error } => {
+                assert_eq!(file_name, "tests/models/missing.mo");
+                assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+            }
+            _ => panic!("expected I/O error"),
+        }
+        assert!(err
+            .to_string()
+            .starts_with("failed to read tests/models/missing.mo"));
+    }
+
+    #[test]
+    fn test_lexical_error() {
+        let err = parse("test.mo", "model A Real x$; end A;").unwrap_err();
+        assert_eq!(err.errors.len(), 1);
+        assert_eq!(err.errors[0].span, (14, 15));
+        assert_eq!(err.errors[0].code(), "E005");
+
+        // the offending input is skipped, and parsing goes on
+        let (def, diagnostics) =
+            parse_with_recovery("test.mo", "model A Real x$; Real y = ; end A;");
+        assert_eq!(diagnostics.errors.len(), 2);
+        assert_eq!(diagnostics.errors[0].code(), "E005");
+        assert_eq!(diagnostics.errors[1].code(), "E001");
+        assert!(def.unwrap().classes["A"].components.contains_key("x"));
+    }
 }
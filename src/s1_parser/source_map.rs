@@ -0,0 +1,137 @@
+//! This module maps the byte spans of the AST to lines and columns.
+//!
+//! Lines and columns start at 0, as in the Language Server Protocol.
+//! Columns are counted in UTF-8 bytes, or in UTF-16 code units for
+//! clients that expect them.
+
+use crate::s1_parser::ast::part::NodeData;
+
+/// A position in a file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineCol {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// The line starts of a file, to convert byte offsets to positions.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineIndex {
+    /// Byte offset of the start of each line.
+    line_starts: Vec<usize>,
+    /// Byte offset, UTF-8 and UTF-16 lengths of each character encoded
+    /// on more than one byte, which UTF-16 columns count differently.
+    wide_chars: Vec<(usize, usize, usize)>,
+    len: usize,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> Self {
+        let mut line_starts = vec![0];
+        let mut wide_chars = Vec::new();
+        for (offset, c) in source.char_indices() {
+            if c == '\n' {
+                line_starts.push(offset + 1);
+            } else if !c.is_ascii() {
+                wide_chars.push((offset, c.len_utf8(), c.len_utf16()));
+            }
+        }
+        LineIndex {
+            line_starts,
+            wide_chars,
+            len: source.len(),
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The byte offset of the start of a line, if it exists.
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        self.line_starts.get(line).copied()
+    }
+
+    /// The position of a byte offset, with the column in UTF-8 bytes.
+    /// Offsets past the end are clamped to the end of the file.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.len);
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        LineCol {
+            line,
+            col: offset - self.line_starts[line],
+        }
+    }
+
+    /// The position of a byte offset, with the column in UTF-16 code units.
+    pub fn line_col_utf16(&self, offset: usize) -> LineCol {
+        let LineCol { line, col } = self.line_col(offset);
+        let start = self.line_starts[line];
+        let first = self.wide_chars.partition_point(|&(at, ..)| at < start);
+        let last = self
+            .wide_chars
+            .partition_point(|&(at, ..)| at < start + col);
+        let col = self.wide_chars[first..last]
+            .iter()
+            .fold(col, |col, &(_, utf8, utf16)| col - utf8 + utf16);
+        LineCol { line, col }
+    }
+}
+
+/// A file added to a `SourceMap`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+    pub line_index: LineIndex,
+}
+
+/// The files of a parse, indexed by the `file_id` of `NodeData`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SourceMap {
+    pub files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// Adds a file, returning its file id.
+    pub fn add_file(&mut self, name: &str, source: &str) -> usize {
+        self.files.push(SourceFile {
+            name: name.to_string(),
+            source: source.to_string(),
+            line_index: LineIndex::new(source),
+        });
+        self.files.len() - 1
+    }
+
+    pub fn file(&self, file_id: usize) -> Option<&SourceFile> {
+        self.files.get(file_id)
+    }
+
+    /// The source text of a node.
+    ///
+    /// Panics if the file of the node was not added to this map.
+    pub fn text(&self, node_data: &NodeData) -> &str {
+        &self.files[node_data.file_id].source[node_data.span.0..node_data.span.1]
+    }
+
+    /// The start and end positions of a node, with UTF-8 columns.
+    ///
+    /// Panics if the file of the node was not added to this map.
+    pub fn line_col(&self, node_data: &NodeData) -> (LineCol, LineCol) {
+        let index = &self.files[node_data.file_id].line_index;
+        (
+            index.line_col(node_data.span.0),
+            index.line_col(node_data.span.1),
+        )
+    }
+
+    /// The start and end positions of a node, with UTF-16 columns.
+    ///
+    /// Panics if the file of the node was not added to this map.
+    pub fn line_col_utf16(&self, node_data: &NodeData) -> (LineCol, LineCol) {
+        let index = &self.files[node_data.file_id].line_index;
+        (
+            index.line_col_utf16(node_data.span.0),
+            index.line_col_utf16(node_data.span.1),
+        )
+    }
+}
@@ -0,0 +1,22 @@
+//! Helpers shared by the integration tests.
+
+/// Removes node ids and spans from an AST dump, so that it only changes
+/// when the tree does.
+pub fn strip_node_data(dump: &str) -> String {
+    let mut dump = dump.to_string();
+    for (start, end) in [("node_data: NodeData {", "}"), (", id: ", ")")] {
+        while let Some(begin) = dump.find(start) {
+            let mut stop = begin + dump[begin..].find(end).unwrap() + end.len();
+            if dump[stop..].starts_with(", ") {
+                stop += 2;
+            }
+            dump.replace_range(begin..stop, "");
+        }
+    }
+    dump.lines()
+        .filter(|line| line.trim() != ",")
+        .map(|line| line.replace("{  }", "{}").replace(" { }", " {}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
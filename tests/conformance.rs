@@ -0,0 +1,175 @@
+//! Grammar conformance suite.
+//!
+//! Every `tests/conformance/*.mo` snippet is parsed, and the dump of its
+//! classes, without node ids and spans, is compared against the `.ast` file
+//! next to it. Set `UPDATE_EXPECT=1` to regenerate the expected dumps.
+
+mod common;
+
+use common::strip_node_data;
+use rumoca_parser::ast::{node, part};
+use rumoca_parser::s0_lexer::lexer::Lexer;
+use rumoca_parser::s1_parser::modelica::ExpressionParser;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_conformance() {
+    let update = std::env::var_os("UPDATE_EXPECT").is_some();
+    let mut paths: Vec<_> = fs::read_dir("tests/conformance")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "mo"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    let mut failures = Vec::new();
+    for path in &paths {
+        let source = fs::read_to_string(path).unwrap();
+        let def = match rumoca_parser::parse(&path.to_string_lossy(), &source) {
+            Ok(def) => def,
+            Err(diagnostics) => panic!("{}", diagnostics),
+        };
+        let actual = strip_node_data(&format!("{:#?}\n", def.classes));
+        let expected_path = path.with_extension("ast");
+        if update {
+            fs::write(&expected_path, &actual).unwrap();
+        } else if !Path::new(&expected_path).exists() {
+            failures.push(format!(
+                "{}: missing expected dump",
+                expected_path.display()
+            ));
+        } else if fs::read_to_string(&expected_path).unwrap() != actual {
+            failures.push(format!("{}: AST differs", path.display()));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\nrun with UPDATE_EXPECT=1 to regenerate the expected dumps",
+        failures.join("\n")
+    );
+}
+
+/// Renders an expression as an s-expression, e.g. `(Add a (Mul b c))`.
+fn sexpr(expr: &node::Expression) -> String {
+    match expr {
+        node::Expression::Binary(binary) => format!(
+            "({:?} {} {})",
+            binary.op,
+            sexpr(&binary.lhs),
+            sexpr(&binary.rhs)
+        ),
+        node::Expression::Unary(unary) => format!("({:?} {})", unary.op, sexpr(&unary.rhs)),
+        node::Expression::Range(range) => match &range.step {
+            Some(step) => format!(
+                "(Range {} {} {})",
+                sexpr(&range.start),
+                sexpr(step),
+                sexpr(&range.end)
+            ),
+            None => format!("(Range {} {})", sexpr(&range.start), sexpr(&range.end)),
+        },
+        node::Expression::Ref(comp) => comp
+            .parts
+            .iter()
+            .map(|part| part.name.clone())
+            .collect::<Vec<_>>()
+            .join("."),
+        node::Expression::UnsignedInteger(val) => val.val.clone(),
+        node::Expression::UnsignedReal(val) => val.val.clone(),
+        node::Expression::Boolean(val) => val.val.to_string(),
+        node::Expression::FunctionCall(call) => format!(
+            "({} {})",
+            call.comp.parts[0].name,
+            call.args.iter().map(sexpr).collect::<Vec<_>>().join(" ")
+        ),
+        expr => panic!("unsupported expression {:?}", expr),
+    }
+}
+
+fn parse_expression(source: &str) -> Result<node::Expression, String> {
+    let mut context = part::ParserContext::default();
+    let result = ExpressionParser::new().parse(&mut context, Lexer::new(source));
+    match (result, context.errors.is_empty()) {
+        (Ok(expr), true) => Ok(expr),
+        (Ok(_), false) => Err(format!("{:?}", context.errors)),
+        (Err(error), _) => Err(format!("{:?}", error)),
+    }
+}
+
+#[test]
+fn test_precedence() {
+    let cases = [
+        // arithmetic
+        ("a + b * c", "(Add a (Mul b c))"),
+        ("a * b + c", "(Add (Mul a b) c)"),
+        ("a - b - c", "(Sub (Sub a b) c)"),
+        ("a / b * c", "(Mul (Div a b) c)"),
+        ("a .* b .+ c", "(ElemAdd (ElemMul a b) c)"),
+        ("a ./ b .- c", "(ElemSub (ElemDiv a b) c)"),
+        // exponentiation
+        ("a * b ^ c", "(Mul a (Exp b c))"),
+        ("a ^ b * c", "(Mul (Exp a b) c)"),
+        ("a .^ 2", "(ElemExp a 2)"),
+        ("(a ^ b) ^ c", "(Exp (Paren (Exp a b)) c)"),
+        ("a ^ (b ^ c)", "(Exp a (Paren (Exp b c)))"),
+        // unary minus applies to the whole first term
+        ("-a", "(Negative a)"),
+        ("-a + b", "(Add (Negative a) b)"),
+        ("-a - b", "(Sub (Negative a) b)"),
+        ("-a * b", "(Negative (Mul a b))"),
+        ("-a ^ 2", "(Negative (Exp a 2))"),
+        ("+a - b", "(Sub (Positive a) b)"),
+        (".-a .+ b", "(ElemAdd (ElemNegative a) b)"),
+        ("-a < b", "(LessThan (Negative a) b)"),
+        // relations and logical operators
+        ("a + b < c * d", "(LessThan (Add a b) (Mul c d))"),
+        ("a <= b == true", "(Equal (LessThanOrEqual a b) true)"),
+        ("a and b", "(And a b)"),
+        ("a or b", "(Or a b)"),
+        ("a or b and c", "(Or a (And b c))"),
+        ("a and b or c", "(Or (And a b) c)"),
+        ("a and b and c", "(And (And a b) c)"),
+        ("not a and b", "(And (Not a) b)"),
+        ("not a < b", "(Not (LessThan a b))"),
+        ("a > 1 or not b", "(Or (GreaterThan a 1) (Not b))"),
+        // ranges bind loosest and never nest
+        ("1:n", "(Range 1 n)"),
+        ("1:0.1:2", "(Range 1 0.1 2)"),
+        ("a + 1:2 * b", "(Range (Add a 1) (Mul 2 b))"),
+        (
+            "-1:-0.5:-n",
+            "(Range (Negative 1) (Negative 0.5) (Negative n))",
+        ),
+        ("a or b:c", "(Range (Or a b) c)"),
+        ("(1:2):3", "(Range (Paren (Range 1 2)) 3)"),
+        // primaries
+        ("der(x) + f(x, -y)", "(Add (der x) (f x (Negative y)))"),
+        ("a.b[1] * 2.5e-3", "(Mul a.b 2.5e-3)"),
+    ];
+    for (source, expected) in cases {
+        let expr = parse_expression(source).unwrap_or_else(|err| panic!("{}: {}", source, err));
+        assert_eq!(sexpr(&expr), expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_precedence_errors() {
+    for source in [
+        "a ^ b ^ c",
+        "a .^ b ^ c",
+        "- -a",
+        "a * -b",
+        "a ^ -b",
+        "a + -b",
+        "a:b:c:d",
+        "1:2:3:4:5",
+    ] {
+        assert!(
+            parse_expression(source).is_err(),
+            "{} should not parse",
+            source
+        );
+    }
+}
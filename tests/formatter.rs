@@ -0,0 +1,160 @@
+//! Formatting of the models and conformance snippets, and the layout options.
+
+mod common;
+
+use common::strip_node_data;
+use rumoca_parser::formatter::{self, FormatOptions};
+use rumoca_parser::s0_lexer::trivia::tokenize;
+use std::fs;
+
+fn comments(source: &str) -> Vec<&str> {
+    let mut comments: Vec<_> = tokenize(source)
+        .iter()
+        .filter(|token| token.kind.is_comment())
+        .map(|token| token.text(source))
+        .collect();
+    comments.sort();
+    comments
+}
+
+#[test]
+fn test_format_models() {
+    let mut paths: Vec<_> = ["tests/models", "tests/conformance"]
+        .iter()
+        .flat_map(|dir| fs::read_dir(dir).unwrap())
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "mo"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    let options = FormatOptions::default();
+    for path in &paths {
+        let file_name = path.to_string_lossy();
+        let source = fs::read_to_string(path).unwrap();
+        let formatted = formatter::format(&file_name, &source, &options).unwrap();
+        let def = rumoca_parser::parse(&file_name, &source).unwrap();
+        let reparsed = match rumoca_parser::parse(&file_name, &formatted) {
+            Ok(def) => def,
+            Err(diagnostics) => panic!("{}\n{}", formatted, diagnostics),
+        };
+        assert_eq!(
+            strip_node_data(&format!("{:#?}", def.classes)),
+            strip_node_data(&format!("{:#?}", reparsed.classes)),
+            "{} formatted as\n{}",
+            file_name,
+            formatted
+        );
+        assert_eq!(comments(&source), comments(&formatted), "{}", file_name);
+        assert_eq!(
+            formatter::format(&file_name, &formatted, &options).unwrap(),
+            formatted,
+            "{}",
+            file_name
+        );
+    }
+}
+
+const SOURCE: &str = r#"within Library; // examples
+// a model
+model M "doc"
+
+  parameter Real k=1 "gain";  Real x, y "outputs";
+  Real longer_name(start=0, fixed=true) "state";
+
+  Modelica.Blocks.Sources.Step step(height = 1.0, offset = 0.0, startTime = 0.5) "input";
+equation
+  if x > 0 then // positive
+    y = 1;
+  else
+    y = 2;
+  end if;
+
+
+  der(longer_name) = -k*longer_name + step.y; // decay
+  /* before the end */
+end M;"#;
+
+#[test]
+fn test_format_defaults() {
+    let expected = r#"within Library; // examples
+
+// a model
+model M "doc"
+    parameter Real k = 1                      "gain";
+    Real x;
+    Real y                                    "outputs";
+    Real longer_name(start = 0, fixed = true) "state";
+
+    Modelica.Blocks.Sources.Step step(height = 1.0, offset = 0.0, startTime = 0.5) "input";
+equation
+    if x > 0 then // positive
+        y = 1;
+    else
+        y = 2;
+    end if;
+
+    der(longer_name) = -k * longer_name + step.y; // decay
+    /* before the end */
+end M;
+"#;
+    let formatted = formatter::format("M.mo", SOURCE, &FormatOptions::default()).unwrap();
+    assert_eq!(formatted, expected);
+}
+
+#[test]
+fn test_format_options() {
+    let options = FormatOptions {
+        indent_width: 2,
+        line_width: 60,
+        split_declarations: false,
+        align_descriptions: false,
+    };
+    let expected = r#"within Library; // examples
+
+// a model
+model M "doc"
+  parameter Real k = 1 "gain";
+  Real x, y "outputs";
+  Real longer_name(start = 0, fixed = true) "state";
+
+  Modelica.Blocks.Sources.Step step(height = 1.0,
+    offset = 0.0, startTime = 0.5) "input";
+equation
+  if x > 0 then // positive
+    y = 1;
+  else
+    y = 2;
+  end if;
+
+  der(longer_name) = -k * longer_name + step.y; // decay
+  /* before the end */
+end M;
+"#;
+    let formatted = formatter::format("M.mo", SOURCE, &options).unwrap();
+    assert_eq!(formatted, expected);
+}
+
+#[test]
+fn test_diff() {
+    assert_eq!(formatter::diff("a.mo", "a\nb\n", "a\nb\n"), "");
+    assert_eq!(
+        formatter::diff(
+            "a.mo",
+            "1\n2\n3\n4\n5\n6\n7\n8\n9\n",
+            "1\n2\nthree\n4\n5\n6\n7\n8\n"
+        ),
+        "--- a.mo\n+++ a.mo\n\
+         @@ -1,9 +1,8 @@\n 1\n 2\n-3\n+three\n 4\n 5\n 6\n 7\n 8\n-9\n"
+    );
+    assert_eq!(
+        formatter::diff(
+            "a.mo",
+            "1\n2\n3\n4\n5\n6\n7\n8\n9\n10",
+            "one\n2\n3\n4\n5\n6\n7\n8\n9\n10\n"
+        ),
+        "--- a.mo\n+++ a.mo\n\
+         @@ -1,4 +1,4 @@\n-1\n+one\n 2\n 3\n 4\n\
+         @@ -7,4 +7,4 @@\n 7\n 8\n 9\n-10\n\\ No newline at end of file\n+10\n"
+    );
+}
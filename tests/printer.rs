@@ -0,0 +1,105 @@
+//! Round trip of the printer over the models and conformance snippets.
+
+mod common;
+
+use common::strip_node_data;
+use rumoca_parser::ast::{node, part, printer};
+use std::fs;
+
+#[test]
+fn test_print_round_trip() {
+    let mut paths: Vec<_> = ["tests/models", "tests/conformance"]
+        .iter()
+        .flat_map(|dir| fs::read_dir(dir).unwrap())
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "mo"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in &paths {
+        let file_name = path.to_string_lossy();
+        let def = rumoca_parser::parse_file(&file_name).unwrap();
+        let printed = printer::print(&def);
+        let reparsed = match rumoca_parser::parse(&file_name, &printed) {
+            Ok(def) => def,
+            Err(diagnostics) => panic!("{}\n{}", printed, diagnostics),
+        };
+        assert_eq!(def.within, reparsed.within, "{}", file_name);
+        assert_eq!(
+            strip_node_data(&format!("{:#?}", def.classes)),
+            strip_node_data(&format!("{:#?}", reparsed.classes)),
+            "{} printed as\n{}",
+            file_name,
+            printed
+        );
+        assert_eq!(printed, printer::print(&reparsed), "{}", file_name);
+    }
+}
+
+fn reference(name: &str) -> node::Expression {
+    node::Expression::Ref(node::ComponentReference {
+        parts: vec![node::RefPart {
+            name: name.to_string(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    })
+}
+
+fn binary(op: part::BinaryOp, lhs: node::Expression, rhs: node::Expression) -> node::Expression {
+    node::Expression::Binary(node::Binary {
+        op,
+        lhs: Box::new(lhs),
+        rhs: Box::new(rhs),
+        ..Default::default()
+    })
+}
+
+fn negative(rhs: node::Expression) -> node::Expression {
+    node::Expression::Unary(node::Unary {
+        op: part::UnaryOp::Negative,
+        rhs: Box::new(rhs),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_print_inserts_parentheses() {
+    use part::BinaryOp::*;
+    let (a, b, c) = (reference("a"), reference("b"), reference("c"));
+    let cases = [
+        (
+            binary(Add, a.clone(), binary(Mul, b.clone(), c.clone())),
+            "a + b * c",
+        ),
+        (
+            binary(Mul, binary(Add, a.clone(), b.clone()), c.clone()),
+            "(a + b) * c",
+        ),
+        (
+            binary(Sub, binary(Sub, a.clone(), b.clone()), c.clone()),
+            "a - b - c",
+        ),
+        (
+            binary(Sub, a.clone(), binary(Sub, b.clone(), c.clone())),
+            "a - (b - c)",
+        ),
+        (
+            binary(Exp, binary(Exp, a.clone(), b.clone()), c.clone()),
+            "(a ^ b) ^ c",
+        ),
+        (binary(Add, negative(a.clone()), b.clone()), "-a + b"),
+        (binary(Add, a.clone(), negative(b.clone())), "a + (-b)"),
+        (binary(Mul, negative(a.clone()), b.clone()), "(-a) * b"),
+        (negative(binary(Mul, a.clone(), b.clone())), "-a * b"),
+        (negative(binary(Add, a.clone(), b.clone())), "-(a + b)"),
+        (
+            binary(And, binary(Or, a.clone(), b.clone()), c.clone()),
+            "(a or b) and c",
+        ),
+    ];
+    for (expr, expected) in cases {
+        assert_eq!(printer::print_expression(&expr), expected);
+    }
+}